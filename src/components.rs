@@ -1,3 +1,10 @@
 pub mod dashboard;
 mod data;
+pub mod devices;
+mod midi_file;
+pub mod monitor;
+pub mod permission;
 pub mod preset;
+pub mod routing;
+pub mod toast;
+pub mod translator;
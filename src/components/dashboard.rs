@@ -2,14 +2,42 @@ use dioxus::prelude::*;
 use dioxus_router::prelude::*;
 
 use crate::components::data::{use_persistent, AppData};
+use crate::components::devices::{use_outputs, DeviceStatusView};
+use crate::components::midi_file::import_preset;
+use crate::components::permission::PermissionView;
 use crate::components::preset::Button;
-use crate::midi::midi_message::send_midi_messages;
+use crate::components::toast::{show, show_failure, use_toasts};
+use crate::midi::midi_message::panic;
+use crate::midi::scheduler;
+use crate::midi::scheduler::{play, Progress};
 use crate::Route;
 
 #[component]
 pub fn DashboardView(cx: Scope) -> Element {
     let data = use_persistent(cx, "app_data", || AppData::default());
     let in_edit_mode = use_state(cx, || false);
+    let progress = use_state(cx, || None::<(usize, Progress)>);
+    let outputs = use_outputs(cx);
+    let nav = use_navigator(cx);
+    let toasts = use_toasts(cx);
+    let missing: Vec<bool> = data
+        .get()
+        .presets
+        .iter()
+        .map(|preset| !preset.devices().all(|device| device.is_available(&outputs)))
+        .collect();
+
+    // Presets from before devices had names store port indices; swap those for port names as
+    // soon as the ports are known.
+    use_effect(cx, (&outputs,), |(outputs,)| {
+        let data = data.clone();
+        async move {
+            let migrated = data.get().migrate_devices(&outputs);
+            if migrated != data.get() {
+                data.set(migrated);
+            }
+        }
+    });
 
     cx.render(rsx!(
         div { class: "container bg-slate-50 mx-auto px-2 pt-2 text-sm mb-4",
@@ -22,6 +50,74 @@ pub fn DashboardView(cx: Scope) -> Element {
                         data.set(data.get().new_preset(Default::default()));
                     }
                 }
+                label { class: "border-2 rounded-md flex text-sm items-center px-2 py-2 hover:bg-slate-200 hover:cursor-pointer md:px-5",
+                    span { class: "fas fa-file-import mr-1" }
+                    "Import MIDI File"
+                    input {
+                        r#type: "file",
+                        accept: ".mid,.midi",
+                        class: "hidden",
+                        onchange: move |evt| {
+                            if let Some(files) = evt.files.clone() {
+                                let data = data.clone();
+                                let toasts = toasts.clone();
+                                cx.spawn(async move {
+                                    match import_preset(files).await {
+                                        Ok(preset) => data.set(data.get().new_preset(preset)),
+                                        Err(err) => {
+                                            log::error!("Error importing MIDI file: {}", err);
+                                            show(&toasts, "MIDI file import failed".to_string(), err.to_string());
+                                        }
+                                    }
+                                });
+                            }
+                        }
+                    }
+                }
+                Button {
+                    text: "Stop",
+                    icon: "fas fa-stop",
+                    styling: "",
+                    on_click: move |_| {
+                        scheduler::cancel();
+                        progress.set(None);
+                    }
+                }
+                Button {
+                    text: "Panic",
+                    icon: "fas fa-triangle-exclamation",
+                    styling: "bg-red-300 hover:bg-red-500 border-red-500",
+                    on_click: move |_| {
+                        for err in panic() {
+                            show(toasts, "Panic failed".to_string(), err.to_string());
+                        }
+                        progress.set(None);
+                    }
+                }
+                Button {
+                    text: "Monitor",
+                    icon: "fas fa-wave-square",
+                    styling: "",
+                    on_click: move |_| {
+                        nav.push(Route::MonitorView {});
+                    }
+                }
+                Button {
+                    text: "Triggers",
+                    icon: "fas fa-shuffle",
+                    styling: "",
+                    on_click: move |_| {
+                        nav.push(Route::TranslatorView {});
+                    }
+                }
+                Button {
+                    text: "Routing",
+                    icon: "fas fa-route",
+                    styling: "",
+                    on_click: move |_| {
+                        nav.push(Route::RoutingView {});
+                    }
+                }
                 Button {
                     text: "Toggle Edit Mode",
                     icon: { if *in_edit_mode.get() { "fas fa-pen-to-square" } else { "fa fa-pen-to-square" } },
@@ -48,16 +144,46 @@ pub fn DashboardView(cx: Scope) -> Element {
                     }
                 }
             }
+            PermissionView { sysex: data.get().needs_sysex() }
+            DeviceStatusView {
+                devices: data.get().presets.iter().flat_map(|preset| preset.devices().cloned()).collect()
+            }
             div { class: "grid grid-cols-2 gap-2 md:grid-cols-3 md:gap-4 mt-4",
-                for (i , preset) in data.get().presets.into_iter().enumerate() {
+                for (i , (preset , missing)) in data.get().presets.into_iter().zip(missing).enumerate() {
                     if !*in_edit_mode.get() {
                         rsx! (
                     div {
                         class: "block max-w-sm p-6 border-2 border-{preset.card_colour}-700 shadow-lg rounded-lg bg-{preset.card_colour}-300 hover:bg-{preset.card_colour}-500 transition duration-200 ease-in-out hover:cursor-pointer",
-                                onclick: move |_| {send_midi_messages(preset.device_index, preset.messages.clone())},
+                                onclick: move |_| {
+                                    let progress = progress.clone();
+                                    let toasts = toasts.clone();
+                                    let label = preset.label.clone();
+                                    let settings = data.get();
+                                    play(
+                                        &preset.device,
+                                        preset.messages.clone(),
+                                        |device| settings.device_settings(device).bank_select,
+                                        move |p| progress.set(Some((i, p))),
+                                        move |failure| show_failure(&toasts, &label, &failure),
+                                    )
+                                },
                         h5 { class: "mb-2 text-2xl font-bold text-center tracking-tight text-gray-900",
                             "{preset.label}"
                         }
+                        if missing {
+                            rsx!(DeviceMissingBadge {})
+                        }
+                        match progress.get() {
+                            Some((running, p)) if *running == i && !p.is_done() => rsx!(
+                                div { class: "h-1 rounded-full bg-white/50",
+                                    div {
+                                        class: "h-1 rounded-full bg-gray-800",
+                                        style: "width: {p.percent()}%"
+                                    }
+                                }
+                            ),
+                            _ => rsx!(""),
+                        }
                     })
                         }
                     else {
@@ -67,6 +193,9 @@ pub fn DashboardView(cx: Scope) -> Element {
                         class: "block max-w-sm p-6 border-2 border-{preset.card_colour}-700 shadow-lg rounded-lg bg-{preset.card_colour}-300 hover:bg-{preset.card_colour}-500 transition duration-200 ease-in-out",
                         h5 { class: "mb-2 text-2xl font-bold text-center tracking-tight text-gray-900",
                             "{preset.label}"
+                        }
+                        if missing {
+                            rsx!(DeviceMissingBadge {})
                         }
                                 span { class: "fas fa-pen"}
                     })
@@ -76,3 +205,15 @@ pub fn DashboardView(cx: Scope) -> Element {
         }
     ))
 }
+
+#[component]
+fn DeviceMissingBadge(cx: Scope) -> Element {
+    cx.render(rsx!(
+        div { class: "mb-2 text-center",
+            span { class: "inline-flex items-center rounded-md bg-red-50 px-2 py-1 text-xs font-medium text-red-700 ring-1 ring-inset ring-red-600/20",
+                span { class: "fas fa-plug-circle-xmark mr-1" }
+                "Device missing"
+            }
+        }
+    ))
+}
@@ -3,21 +3,41 @@ use dioxus::hooks::{use_ref, UseRef};
 use gloo_storage::{LocalStorage, Storage};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-use crate::midi::midi_message::MidiMessage;
+use crate::midi::bank_select::BankSelectMode;
+use crate::midi::device::DeviceRef;
+use crate::midi::midi_message::PresetMessage;
+use crate::midi::routing::ThruRoute;
+use crate::midi::translator::MessagePattern;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AppData {
     pub presets: Vec<Preset>,
+    #[serde(default)]
+    pub devices: HashMap<String, DeviceSettings>,
+    #[serde(default)]
+    pub rules: Vec<TriggerRule>,
+    #[serde(default)]
+    pub routes: Vec<ThruRoute>,
 }
 
 impl Default for AppData {
     fn default() -> Self {
-        AppData { presets: vec![] }
+        AppData {
+            presets: vec![],
+            devices: HashMap::new(),
+            rules: vec![],
+            routes: vec![],
+        }
     }
 }
 
 impl AppData {
+    /// The data as last saved, for code running outside of a component.
+    pub fn stored() -> AppData {
+        LocalStorage::get("app_data").unwrap_or_default()
+    }
     pub fn update_preset(self, index: usize, preset: Preset) -> Self {
         let mut presets = self.presets;
         presets[index] = preset;
@@ -28,13 +48,94 @@ impl AppData {
         presets.push(preset);
         AppData { presets, ..self }
     }
+    /// Removes a preset, along with the rules that trigger it.
+    pub fn remove_preset(self, index: usize) -> Self {
+        let mut presets = self.presets;
+        presets.remove(index);
+        let rules = self
+            .rules
+            .into_iter()
+            .filter(|rule| rule.preset != index)
+            .map(|rule| TriggerRule {
+                preset: if rule.preset > index {
+                    rule.preset - 1
+                } else {
+                    rule.preset
+                },
+                ..rule
+            })
+            .collect();
+        AppData {
+            presets,
+            rules,
+            ..self
+        }
+    }
+    pub fn update_device(self, name: String, settings: DeviceSettings) -> Self {
+        let mut devices = self.devices;
+        devices.insert(name, settings);
+        AppData { devices, ..self }
+    }
+    /// Whether any preset sends SysEx.
+    pub fn needs_sysex(&self) -> bool {
+        self.presets.iter().any(Preset::needs_sysex)
+    }
+    /// Settings of `device`, keyed by its port name.
+    pub fn device_settings(&self, device: &DeviceRef) -> DeviceSettings {
+        device
+            .port_name()
+            .and_then(|name| self.devices.get(&name).cloned())
+            .unwrap_or_default()
+    }
+    /// Like `device_settings`, looking a legacy port index up in `outputs` instead of listing
+    /// the ports.
+    pub fn device_settings_in(&self, device: &DeviceRef, outputs: &[String]) -> DeviceSettings {
+        device
+            .port_name_in(outputs)
+            .and_then(|name| self.devices.get(name).cloned())
+            .unwrap_or_default()
+    }
+    /// Replaces the port indices stored by older presets with the names of the ports now at
+    /// those indices.
+    pub fn migrate_devices(self, outputs: &[String]) -> Self {
+        let presets = self
+            .presets
+            .into_iter()
+            .map(|preset| Preset {
+                device: preset.device.resolve(outputs),
+                messages: preset
+                    .messages
+                    .into_iter()
+                    .map(|step| PresetMessage {
+                        device: step.device.map(|device| device.resolve(outputs)),
+                        ..step
+                    })
+                    .collect(),
+                ..preset
+            })
+            .collect();
+        AppData { presets, ..self }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct DeviceSettings {
+    pub bank_select: BankSelectMode,
+}
+
+/// Plays the preset at index `preset` whenever a message matching `pattern` arrives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct TriggerRule {
+    pub pattern: MessagePattern,
+    pub preset: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Preset {
-    pub device_index: usize,
+    #[serde(alias = "device_index")]
+    pub device: DeviceRef,
     pub label: String,
-    pub messages: Vec<MidiMessage>,
+    pub messages: Vec<PresetMessage>,
     pub card_colour: String,
 }
 
@@ -44,10 +145,22 @@ impl Default for Preset {
             label: "New Preset".to_string(),
             messages: vec![],
             card_colour: "red".to_string(),
-            device_index: 0,
+            device: DeviceRef::default(),
         }
     }
 }
+
+impl Preset {
+    /// Every output this preset sends to.
+    pub fn devices(&self) -> impl Iterator<Item = &DeviceRef> {
+        std::iter::once(&self.device)
+            .chain(self.messages.iter().filter_map(|step| step.device.as_ref()))
+    }
+
+    pub fn needs_sysex(&self) -> bool {
+        self.messages.iter().any(|step| step.message.is_sysex())
+    }
+}
 /// A persistent storage hook that can be used to store data across application reloads.
 #[allow(clippy::needless_return)]
 pub fn use_persistent<T: Serialize + DeserializeOwned + Default + 'static>(
@@ -70,6 +183,7 @@ struct StorageEntry<T> {
     value: T,
 }
 
+#[derive(Clone)]
 pub struct UsePersistent<T: 'static> {
     inner: UseRef<StorageEntry<T>>,
 }
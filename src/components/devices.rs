@@ -0,0 +1,99 @@
+use dioxus::prelude::*;
+
+use crate::midi::device::{output_names, DeviceRef};
+use crate::midi::input;
+use crate::midi::input::input_names;
+use crate::midi::watcher::{watch, watch_inputs};
+
+/// Names of the output ports currently available, kept up to date by `use_device_watcher`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Outputs(pub Vec<String>);
+
+/// Names of the input ports currently available, kept up to date by `use_device_watcher`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Inputs(pub Vec<String>);
+
+/// Provides `Outputs` and `Inputs` to every component below and keeps them in sync with the
+/// connected devices.
+pub fn use_device_watcher(cx: &ScopeState) {
+    use_shared_state_provider(cx, || Outputs(output_names()));
+    use_shared_state_provider(cx, || Inputs(input_names()));
+    let outputs = use_shared_state::<Outputs>(cx).unwrap().clone();
+    let inputs = use_shared_state::<Inputs>(cx).unwrap().clone();
+    cx.use_hook(|| watch(move |names| *outputs.write() = Outputs(names)));
+    cx.use_hook(|| watch_inputs(move |names| *inputs.write() = Inputs(names)));
+}
+
+/// The output ports currently available. Re-renders the component when they change.
+pub fn use_outputs(cx: &ScopeState) -> Vec<String> {
+    use_shared_state::<Outputs>(cx)
+        .map(|outputs| outputs.read().0.clone())
+        .unwrap_or_default()
+}
+
+/// The input ports currently available. Re-renders the component when they change.
+pub fn use_inputs(cx: &ScopeState) -> Vec<String> {
+    use_shared_state::<Inputs>(cx)
+        .map(|inputs| inputs.read().0.clone())
+        .unwrap_or_default()
+}
+
+/// Opens the inputs called `names` that are currently available.
+pub fn open_inputs<'a>(names: impl IntoIterator<Item = &'a String>, inputs: &[String]) {
+    for name in names.into_iter().filter(|name| inputs.contains(name)) {
+        if let Err(err) = input::open(name) {
+            log::error!("Error opening MIDI input {}: {}", name, err);
+        }
+    }
+}
+
+/// Shows whether each device used by a preset is connected, followed by any other outputs.
+#[component]
+pub fn DeviceStatusView(cx: Scope, devices: Vec<DeviceRef>) -> Element<'a> {
+    let outputs = use_outputs(cx);
+    let mut used: Vec<(String, bool)> = vec![];
+    for device in devices {
+        let label = device.to_string();
+        if !used.iter().any(|(known, _)| known == &label) {
+            used.push((label, device.is_available(&outputs)));
+        }
+    }
+    // Resolved against the ports from the last watcher tick, so rendering never lists them.
+    let ports: Vec<&str> = devices
+        .iter()
+        .filter_map(|device| device.port_name_in(&outputs))
+        .collect();
+    let others: Vec<String> = outputs
+        .iter()
+        .filter(|name| !ports.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    cx.render(rsx!(
+        div { class: "flex flex-wrap gap-2 mt-4",
+            for (label , connected) in used.into_iter() {
+                DeviceStatusBadge { label: label, connected: connected }
+            }
+            for name in others.into_iter() {
+                DeviceStatusBadge { label: name, connected: true }
+            }
+        }
+    ))
+}
+
+#[component]
+fn DeviceStatusBadge(cx: Scope, label: String, connected: bool) -> Element<'a> {
+    let (dot, text) = if *connected {
+        ("bg-green-500", "text-gray-700")
+    } else {
+        ("bg-red-500", "text-red-700")
+    };
+    cx.render(rsx!(
+        span { class: "inline-flex items-center rounded-md bg-gray-50 px-2 py-1 text-xs font-medium {text} ring-1 ring-inset ring-gray-500/10",
+            span { class: "h-2 w-2 rounded-full mr-1 {dot}" }
+            "{label}"
+            if !*connected {
+                rsx!(" (disconnected)")
+            }
+        }
+    ))
+}
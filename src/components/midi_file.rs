@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use dioxus::html::FileEngine;
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, HtmlAnchorElement, Url};
+
+use crate::components::data::Preset;
+use crate::midi::midi_message::PresetMessage;
+use crate::midi::smf::{MidiFile, SmfError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    NoFile,
+    Unreadable(String),
+    Invalid { file: String, error: SmfError },
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::NoFile => write!(f, "No file selected"),
+            ImportError::Unreadable(file) => write!(f, "Could not read {}", file),
+            ImportError::Invalid { file, error } => write!(f, "{}: {}", file, error),
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+/// Reads the first selected file and turns it into a new preset named after its track name,
+/// or the file name if it has none.
+pub async fn import_preset(files: Arc<dyn FileEngine>) -> Result<Preset, ImportError> {
+    let file_name = files
+        .files()
+        .into_iter()
+        .next()
+        .ok_or(ImportError::NoFile)?;
+    let bytes = files
+        .read_file(&file_name)
+        .await
+        .ok_or_else(|| ImportError::Unreadable(file_name.clone()))?;
+    let midi_file = MidiFile::parse(&bytes).map_err(|error| ImportError::Invalid {
+        file: file_name.clone(),
+        error,
+    })?;
+    let label = midi_file.name.unwrap_or_else(|| {
+        file_name
+            .trim_end_matches(".midi")
+            .trim_end_matches(".mid")
+            .to_string()
+    });
+    Ok(Preset {
+        label,
+        messages: midi_file
+            .messages
+            .into_iter()
+            .map(PresetMessage::from)
+            .collect(),
+        ..Preset::default()
+    })
+}
+
+/// Hands `bytes` to the browser as a file download. Fails with the browser's reason.
+pub fn download(file_name: &str, bytes: &[u8]) -> Result<(), String> {
+    save(file_name, bytes).map_err(|err| err.as_string().unwrap_or_else(|| format!("{:?}", err)))
+}
+
+fn save(file_name: &str, bytes: &[u8]) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("No document")?;
+    let blob = Blob::new_with_u8_array_sequence(&Array::of1(&Uint8Array::from(bytes)))?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Url::revoke_object_url(&url)
+}
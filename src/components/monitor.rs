@@ -0,0 +1,196 @@
+use std::cell::Cell;
+
+use dioxus::prelude::*;
+use dioxus_router::prelude::use_navigator;
+use gloo_timers::callback::Interval;
+
+use crate::components::devices::use_inputs;
+use crate::components::midi_file::download;
+use crate::components::preset::Button;
+use crate::components::toast::{show, use_toasts};
+use crate::midi::channel::MidiChannel;
+use crate::midi::input;
+use crate::midi::monitor;
+use crate::midi::monitor::{describe, Direction, MonitorEntry};
+use crate::Route;
+
+/// How often views check their log for new entries.
+const REFRESH_INTERVAL_MS: u32 = 200;
+
+/// Re-renders the component whenever `version` changes. Log entries are added outside of any
+/// event handler, so views showing a log poll its version instead.
+pub fn use_refresh(cx: &ScopeState, version: fn() -> u64) {
+    cx.use_hook(|| {
+        let update = cx.schedule_update();
+        let seen = Cell::new(version());
+        Interval::new(REFRESH_INTERVAL_MS, move || {
+            if seen.replace(version()) != version() {
+                update();
+            }
+        })
+    });
+}
+
+#[component]
+pub fn MonitorView(cx: Scope) -> Element {
+    let nav = use_navigator(cx);
+    let inputs = use_inputs(cx);
+    let toasts = use_toasts(cx);
+    let paused = use_state(cx, monitor::is_paused);
+    let kind = use_state(cx, String::new);
+    let channel = use_state(cx, || None::<MidiChannel>);
+    let direction = use_state(cx, || None::<Direction>);
+
+    use_refresh(cx, monitor::version);
+
+    let entries = monitor::entries();
+    let mut kinds: Vec<&str> = vec![];
+    for entry in entries.iter() {
+        if !kinds.contains(&entry.message.kind()) {
+            kinds.push(entry.message.kind());
+        }
+    }
+    let shown: Vec<MonitorEntry> = entries
+        .into_iter()
+        .rev()
+        .filter(|entry| kind.is_empty() || entry.message.kind() == kind.as_str())
+        .filter(|entry| channel.is_none() || entry.message.channel() == *channel.get())
+        .filter(|entry| direction.is_none() || Some(entry.direction) == *direction.get())
+        .collect();
+    let open: Vec<bool> = inputs.iter().map(|name| input::is_open(name)).collect();
+
+    cx.render(rsx!(
+        div { class: "container bg-slate-50 mx-auto px-2 pt-2 text-sm mb-4",
+            div { class: "flex",
+                Button {
+                    styling: "",
+                    text: "Back to Dashboard",
+                    icon: "fas fa-rotate-back",
+                    on_click: move |_| {
+                        nav.push(Route::DashboardView {});
+                    }
+                }
+                Button {
+                    styling: "",
+                    text: if *paused.get() { "Resume" } else { "Pause" },
+                    icon: if *paused.get() { "fas fa-play" } else { "fas fa-pause" },
+                    on_click: move |_| {
+                        monitor::set_paused(!*paused.get());
+                        paused.set(!*paused.get());
+                    }
+                }
+                Button {
+                    styling: "",
+                    text: "Clear",
+                    icon: "fas fa-trash",
+                    on_click: move |_| monitor::clear()
+                }
+                Button {
+                    styling: "",
+                    text: "Export",
+                    icon: "fas fa-file-export",
+                    on_click: move |_| {
+                        if let Err(err) = download("midi-monitor.tsv", monitor::export().as_bytes()) {
+                            log::error!("Error exporting monitor log: {}", err);
+                            show(toasts, "Monitor export failed".to_string(), err);
+                        }
+                    }
+                }
+            }
+            div { class: "flex flex-wrap gap-2 mt-4",
+                span { class: "flex items-center", "Inputs:" }
+                if inputs.is_empty() {
+                    rsx!(span { class: "flex items-center text-gray-600", "No MIDI inputs found" })
+                }
+                for (name , open) in inputs.into_iter().zip(open) {
+                    InputToggle { name: name, open: open }
+                }
+            }
+            div { class: "flex mt-4",
+                select {
+                    class: "flex rounded-md border-2 px-2 py-2",
+                    onchange: move |e| kind.set(e.value.to_string()),
+                    option { selected: kind.is_empty(), value: "", "All Types" }
+                    for k in kinds.into_iter() {
+                        option { selected: kind.as_str() == k, value: "{k}", "{k}" }
+                    }
+                }
+                select {
+                    class: "flex rounded-md border-2 px-2 py-2 ml-2",
+                    onchange: move |e| channel.set(e.value.parse().ok().and_then(|n| MidiChannel::from_number(n).ok())),
+                    option { selected: channel.is_none(), value: "", "All Channels" }
+                    for c in MidiChannel::all() {
+                        option { selected: *channel.get() == Some(c), value: "{c}", "Channel {c}" }
+                    }
+                }
+                select {
+                    class: "flex rounded-md border-2 px-2 py-2 ml-2",
+                    onchange: move |e| {
+                        direction.set(match e.value.as_str() {
+                            "In" => Some(Direction::In),
+                            "Out" => Some(Direction::Out),
+                            _ => None,
+                        })
+                    },
+                    option { selected: direction.is_none(), value: "", "In and Out" }
+                    option { selected: *direction.get() == Some(Direction::In), value: "In", "In" }
+                    option { selected: *direction.get() == Some(Direction::Out), value: "Out", "Out" }
+                }
+            }
+            table { class: "table-auto w-full mt-4 font-mono text-xs",
+                thead {
+                    tr { class: "text-left border-b-2",
+                        th { "Time (ms)" }
+                        th { "Dir" }
+                        th { "Port" }
+                        th { "Type" }
+                        th { "Ch" }
+                        th { "Message" }
+                        th { "Bytes" }
+                    }
+                }
+                tbody {
+                    for entry in shown.into_iter() {
+                        tr { class: "border-b",
+                            td { format!("{:.1}", entry.timestamp) }
+                            td { "{entry.direction}" }
+                            td { "{entry.port}" }
+                            td { entry.message.kind() }
+                            td { entry.message.channel().map(|c| c.to_string()).unwrap_or_default() }
+                            td { describe(&entry.message) }
+                            td { entry.hex() }
+                        }
+                    }
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn InputToggle(cx: Scope, name: String, open: bool) -> Element<'a> {
+    let open = use_state(cx, || *open);
+    let styling = if *open.get() {
+        "bg-green-200 hover:bg-green-300 border-green-500"
+    } else {
+        ""
+    };
+    cx.render(rsx!(
+        button {
+            class: "border-2 rounded-md flex text-sm items-center px-2 py-2 hover:bg-slate-200 {styling}",
+            onclick: move |_| {
+                if *open.get() {
+                    input::close(name);
+                    open.set(false);
+                } else {
+                    match input::open(name) {
+                        Ok(_) => open.set(true),
+                        Err(err) => log::error!("Error opening MIDI input {}: {}", name, err),
+                    }
+                }
+            },
+            span { class: if *open.get() { "fas fa-circle-dot mr-1" } else { "far fa-circle mr-1" } }
+            "{name}"
+        }
+    ))
+}
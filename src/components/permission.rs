@@ -0,0 +1,113 @@
+use dioxus::prelude::*;
+
+use crate::components::data::AppData;
+use crate::components::preset::Button;
+use crate::components::toast::{show, use_toasts, Toasts};
+use crate::midi::error::MidiError;
+use crate::midi::permission;
+use crate::midi::permission::Permission;
+
+/// MIDI access as last reported by the browser, and why SysEx was refused if it was.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MidiPermission {
+    pub permission: Permission,
+    pub sysex_refused: Option<String>,
+}
+
+/// Provides `MidiPermission` to every component below and requests MIDI access, including SysEx
+/// if a stored preset sends it.
+pub fn use_permission_provider(cx: &ScopeState) {
+    use_shared_state_provider(cx, MidiPermission::default);
+    let state = use_shared_state::<MidiPermission>(cx).unwrap();
+    let toasts = use_toasts(cx);
+    cx.use_hook(|| request(state, toasts, AppData::stored().needs_sysex()));
+}
+
+/// Requests MIDI access, with SysEx if `sysex` is set, and reports a refusal as a notification.
+pub fn request(
+    state: &UseSharedState<MidiPermission>,
+    toasts: &UseSharedState<Toasts>,
+    sysex: bool,
+) {
+    let state = state.clone();
+    let toasts = toasts.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = permission::request(sysex).await;
+        {
+            let mut state = state.write();
+            state.permission = permission::permission();
+            if sysex {
+                state.sysex_refused = match &result {
+                    Err(MidiError::SysExDenied(reason)) => Some(reason.clone()),
+                    _ => None,
+                };
+            }
+        }
+        match result {
+            Ok(()) => (),
+            Err(MidiError::SysExDenied(_)) => show(
+                &toasts,
+                "SysEx access denied".to_string(),
+                "SysEx and Fractal SysEx messages are skipped; everything else is still sent."
+                    .to_string(),
+            ),
+            Err(err) => show(&toasts, "MIDI access denied".to_string(), err.to_string()),
+        }
+    });
+}
+
+/// Explains what cannot be sent without MIDI access, or without SysEx access when `sysex` is
+/// set, with a button to ask again. SysEx is asked for as soon as `sysex` is set, unless it was
+/// refused before.
+#[component]
+pub fn PermissionView(cx: Scope, sysex: bool) -> Element<'a> {
+    let state = use_shared_state::<MidiPermission>(cx).unwrap();
+    let toasts = use_toasts(cx);
+    let current = state.read().clone();
+
+    use_effect(cx, (sysex, &current.permission), |(sysex, permission)| {
+        let state = state.clone();
+        let toasts = toasts.clone();
+        async move {
+            if sysex
+                && permission == (Permission::Granted { sysex: false })
+                && state.read().sysex_refused.is_none()
+            {
+                request(&state, &toasts, true);
+            }
+        }
+    });
+
+    let (text, action) = match &current.permission {
+        Permission::Denied(reason) => (
+            format!(
+                "MIDI access was denied ({}). Nothing can be sent until it is allowed.",
+                reason
+            ),
+            "Ask Again",
+        ),
+        Permission::Granted { sysex: false } if *sysex => (
+            match &current.sysex_refused {
+                Some(reason) => format!(
+                    "SysEx access was denied ({}). SysEx and Fractal SysEx messages are skipped; everything else is still sent.",
+                    reason
+                ),
+                None => "SysEx and Fractal SysEx messages need SysEx access.".to_string(),
+            },
+            "Allow SysEx",
+        ),
+        _ => return None,
+    };
+    cx.render(rsx!(
+        div { class: "flex items-center rounded-md border-2 border-amber-500 bg-amber-50 p-2 mt-2 text-amber-800",
+            span { class: "fas fa-lock mr-2" }
+            div { class: "flex-1", "{text}" }
+            Button {
+                text: action,
+                icon: "fas fa-unlock",
+                styling: "ml-2 bg-white",
+                on_click: move |_| request(state, toasts, *sysex)
+            }
+        }
+    ))
+}
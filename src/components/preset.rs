@@ -1,15 +1,34 @@
+use std::cell::Cell;
 use std::fmt::{Debug, Display};
 use std::str::FromStr;
 
 use dioxus::prelude::*;
 use dioxus_router::prelude::use_navigator;
 use gloo_storage::Storage;
-use midir::MidiOutput;
 
-use crate::components::data::{use_persistent, AppData, Preset};
+use crate::components::data::{use_persistent, AppData, DeviceSettings, Preset};
+use crate::components::devices::{use_inputs, use_outputs};
+use crate::components::midi_file::download;
+use crate::components::permission::PermissionView;
+use crate::components::toast::{show, show_failure, use_toasts};
+use crate::midi::bank_select::{BankProgramChange, BankSelectMode};
+use crate::midi::channel::MidiChannel;
+use crate::midi::clock;
+use crate::midi::clock::SongPosition;
 use crate::midi::control_change::ControlChange;
-use crate::midi::midi_message::{get_available_ports, send_midi_messages, MidiMessage};
+use crate::midi::device::DeviceRef;
+use crate::midi::fractal::sysex::{FractalCommand, FractalMessage, FractalModel};
+use crate::midi::input;
+use crate::midi::input::Listener;
+use crate::midi::midi_message::{send_midi_messages, MidiMessage, PresetMessage};
+use crate::midi::note::{note_name, NoteOff, NoteOn};
+use crate::midi::parameter_number::{ControlChange14, Nrpn, Rpn};
+use crate::midi::pitch_bend::PitchBend;
+use crate::midi::pressure::{ChannelPressure, PolyPressure};
 use crate::midi::program_change::ProgramChange;
+use crate::midi::smf::MidiFile;
+use crate::midi::sysex::SysEx;
+use crate::midi::value::{U14, U7};
 use crate::Route;
 
 #[component]
@@ -18,14 +37,36 @@ pub fn PresetView(cx: Scope, id: usize) -> Element {
     let preset = data.get().presets.get(*id).unwrap().clone();
     log::info!("Loaded Preset: {:?}", preset);
 
-    let messages: &UseRef<Vec<MidiMessage>> = use_ref(cx, || preset.messages.clone());
+    let messages: &UseRef<Vec<PresetMessage>> = use_ref(cx, || preset.messages.clone());
     let label: &UseState<String> = use_state(cx, || preset.label.clone());
     let colour: &UseState<String> = use_state(cx, || preset.card_colour.to_string());
     let nav = use_navigator(cx);
+    let toasts = use_toasts(cx);
 
-    let midi_out = MidiOutput::new("My MIDI Output").unwrap();
-    let ports = get_available_ports();
-    let current_port = use_state(cx, || preset.device_index);
+    let outputs = use_outputs(cx);
+    let device = use_state(cx, || preset.device.resolve(&outputs));
+    let bank_select = use_state(cx, || {
+        data.get()
+            .device_settings_in(device.get(), &outputs)
+            .bank_select
+    });
+    let inputs = use_inputs(cx);
+    let learn_input = use_state(cx, || None::<String>);
+    let learning: &UseRef<Option<Learning>> = use_ref(cx, || None);
+
+    // Learn from the first input until another one is picked, or when the picked one goes away.
+    use_effect(cx, (&inputs,), |(inputs,)| {
+        let learn_input = learn_input.clone();
+        async move {
+            if !learn_input
+                .get()
+                .as_ref()
+                .is_some_and(|name| inputs.contains(name))
+            {
+                learn_input.set(inputs.first().cloned());
+            }
+        }
+    });
 
     cx.render(rsx!(
         div { class: "container bg-slate-50 mx-auto px-2 pt-2 text-sm",
@@ -56,19 +97,41 @@ pub fn PresetView(cx: Scope, id: usize) -> Element {
                             colour.set(e);
                         }
                     }
+                    DeviceView {
+                        current: device.get().clone(),
+                        outputs: outputs.clone(),
+                        on_change: {
+                            let outputs = outputs.clone();
+                            move |d: DeviceRef| {
+                                log::info!("Updating device: {}", d);
+                                bank_select.set(data.get().device_settings_in(&d, &outputs).bank_select);
+                                device.set(d);
+                            }
+                        }
+                    }
+                    DeviceAliasInput {
+                        value: device.get().alias().unwrap_or_default().to_string(),
+                        on_change: move |alias: String| {
+                            let alias = Some(alias.trim().to_string()).filter(|a| !a.is_empty());
+                            device.set(device.get().with_alias(alias));
+                        }
+                    }
                     LabeledNumberView {
-                        values: ports
-                            .iter()
+                        values: enum_iterator::all::<BankSelectMode>()
                             .enumerate()
-                            .map(|(i, p)| NumberLabel {
-                                label: midi_out.port_name(p).unwrap_or_else(|_| "Unknown".to_string()),
+                            .map(|(i, mode)| NumberLabel {
+                                label: mode.to_string(),
                                 value: i,
                             })
                             .collect(),
-                        label: "Device Index".to_string(),
-                        current_value: *current_port.get(),
+                        label: "Bank Select".to_string(),
+                        current_value: enum_iterator::all::<BankSelectMode>()
+                            .position(|mode| mode == *bank_select.get())
+                            .unwrap_or(0),
                         on_change: move |i: usize| {
-                            current_port.set(i);
+                            if let Some(mode) = enum_iterator::all::<BankSelectMode>().nth(i) {
+                                bank_select.set(mode);
+                            }
                         }
                     }
                 }
@@ -77,41 +140,82 @@ pub fn PresetView(cx: Scope, id: usize) -> Element {
                         styling: "flex-1 py-4 rounded-l-md rounded-r-none border-l-2 border-y-2 border-r-0",
                         text: "Save Preset",
                         icon: "fas fa-save",
-                        on_click: move |_| {
-                            data.set(
-                                data
-                                    .get()
-                                    .update_preset(
-                                        *id,
-                                        Preset {
-                                            label: label.get().clone(),
-                                            messages: messages.read().clone(),
-                                            card_colour: colour.get().to_string(),
-                                            device_index: current_port.get().clone(),
-                                        },
-                                    ),
-                            );
+                        on_click: {
+                            let outputs = outputs.clone();
+                            move |_| {
+                                let mut updated = data.get().clone();
+                                if let Some(name) = device.get().port_name_in(&outputs) {
+                                    updated = updated
+                                        .update_device(name.to_string(), DeviceSettings { bank_select: *bank_select.get() });
+                                }
+                                data.set(
+                                    updated
+                                        .update_preset(
+                                            *id,
+                                            Preset {
+                                                label: label.get().clone(),
+                                                messages: messages.read().clone(),
+                                                card_colour: colour.get().to_string(),
+                                                device: device.get().clone(),
+                                            },
+                                        ),
+                                );
+                            }
                         }
                     }
                     Button {
                         styling: "flex-1 py-4 rounded-r-md rounded-l-none border-r-2 border-y-2 border-l--0",
                         text: "Test Messages",
                         icon: "fas fa-paper-plane",
-                        on_click: move |_| { send_midi_messages(*current_port.get(), messages.read().clone()) }
+                        on_click: {
+                            let outputs = outputs.clone();
+                            move |_| {
+                                let settings = data.get();
+                                let toasts = toasts.clone();
+                                let label = label.get().clone();
+                                send_midi_messages(
+                                    device.get(),
+                                    messages.read().clone(),
+                                    |step_device| {
+                                        if step_device == device.get() {
+                                            *bank_select.get()
+                                        } else {
+                                            settings.device_settings_in(step_device, &outputs).bank_select
+                                        }
+                                    },
+                                    move |failure| show_failure(&toasts, &label, &failure),
+                                )
+                            }
+                        }
+                    }
+                    Button {
+                        styling: "flex py-4 rounded-lg border-2 ml-2",
+                        text: "Export MIDI File",
+                        icon: "fas fa-file-export",
+                        on_click: move |_| {
+                            let midi_file = MidiFile {
+                                name: Some(label.get().clone()),
+                                messages: messages.read().iter().map(|step| step.message.clone()).collect(),
+                            };
+                            let bytes = midi_file.encode(*bank_select.get());
+                            if let Err(err) = download(&format!("{}.mid", label.get()), &bytes) {
+                                log::error!("Error exporting MIDI file: {}", err);
+                                show(toasts, "MIDI file export failed".to_string(), err);
+                            }
+                        }
                     }
                     Button {
                         styling: "flex py-4 rounded-lg border-2 ml-2",
                         text: "Delete Preset",
                         icon: "fas fa-trash",
                         on_click: move |_| {
-                            let mut presets = data.get().presets.clone();
-                            presets.remove(*id);
-                            data.set(AppData { presets, ..data.get() });
+                            data.set(data.get().remove_preset(*id));
                             nav.replace(Route::DashboardView {});
                         }
                     }
                 }
             }
+            PermissionView { sysex: messages.read().iter().any(|step| step.message.is_sysex()) }
             div { class: "flex mt-2",
                 div {
                     Button {
@@ -119,7 +223,7 @@ pub fn PresetView(cx: Scope, id: usize) -> Element {
                         text: "Add Message",
                         icon: "fas fa-plus",
                         on_click: move |_| {
-                            messages.with_mut(|vec| vec.push(MidiMessage::default()));
+                            messages.with_mut(|vec| vec.push(PresetMessage::default()));
                         }
                     }
                 }
@@ -138,17 +242,82 @@ pub fn PresetView(cx: Scope, id: usize) -> Element {
                         }
                     }
                 }
+                LearnInputView {
+                    current: learn_input.get().clone(),
+                    inputs: inputs.clone(),
+                    on_change: move |name: String| learn_input.set(Some(name))
+                }
             }
             div {
-                for (i , message) in messages.read().iter().enumerate() {
+                for (i , step) in messages.read().iter().enumerate() {
                     MessageView {
                         index: i,
-                        current_value: message.clone(),
+                        current_value: step.message.clone(),
+                        device: step.device.clone(),
+                        outputs: outputs.clone(),
+                        learning: learning.read().as_ref().is_some_and(|learning| learning.step == i),
+                        on_learn: move |_| {
+                            if learning.read().as_ref().is_some_and(|learning| learning.step == i) {
+                                learning.set(None);
+                                return;
+                            }
+                            // Stop learning the previous step first, which may close its input.
+                            learning.set(None);
+                            let Some(port) = learn_input.get().clone() else {
+                                show(toasts, "MIDI learn".to_string(), "No MIDI input to learn from".to_string());
+                                return;
+                            };
+                            let opened = !input::is_open(&port);
+                            if let Err(err) = input::open(&port) {
+                                log::error!("Error opening MIDI input {}: {}", port, err);
+                                show(toasts, format!("Could not open {}", port), err.to_string());
+                                return;
+                            }
+                            let messages = messages.clone();
+                            let done = learning.clone();
+                            let learned = Cell::new(false);
+                            let listener = input::listen({
+                                let port = port.clone();
+                                move |from, message| {
+                                    if from != port || learned.get() {
+                                        return;
+                                    }
+                                    let Some(step) = messages.read().get(i).and_then(|step| step.message.learn(message)) else {
+                                        return;
+                                    };
+                                    log::info!("Learned message {}: {:?}", i, step);
+                                    messages
+                                        .with_mut(|vec| {
+                                            if let Some(current) = vec.get_mut(i) {
+                                                current.message = step;
+                                            }
+                                        });
+                                    learned.set(true);
+                                    // Closing the input from inside its own message handler would
+                                    // drop the handler while it runs.
+                                    let done = done.clone();
+                                    wasm_bindgen_futures::spawn_local(async move { done.set(None) });
+                                }
+                            });
+                            learning
+                                .set(Some(Learning {
+                                    step: i,
+                                    opened: Some(port).filter(|_| opened),
+                                    _listener: listener,
+                                }));
+                        },
                         on_change: move |e: MidiMessage| {
                             log::info!("Updating message {}: {:?}", i, e);
                             messages
                                 .with_mut(|vec| {
-                                    vec[i] = e.clone();
+                                    vec[i].message = e.clone();
+                                });
+                        },
+                        on_device_change: move |device: Option<DeviceRef>| {
+                            log::info!("Updating device of message {}: {:?}", i, device);
+                            messages
+                                .with_mut(|vec| {
+                                    vec[i].device = device.clone();
                                 });
                         }
                     }
@@ -158,6 +327,22 @@ pub fn PresetView(cx: Scope, id: usize) -> Element {
     ))
 }
 
+/// A step waiting for MIDI learn. Dropping it stops listening, and closes the input if learning
+/// opened it.
+struct Learning {
+    step: usize,
+    opened: Option<String>,
+    _listener: Listener,
+}
+
+impl Drop for Learning {
+    fn drop(&mut self) {
+        if let Some(port) = &self.opened {
+            input::close(port);
+        }
+    }
+}
+
 #[component]
 fn ColourSelector<'a>(
     cx: Scope<'a>,
@@ -189,6 +374,23 @@ fn ColourSelector<'a>(
 enum MessageType {
     ProgramChange,
     ControlChange,
+    NoteOn,
+    NoteOff,
+    SysEx,
+    Fractal,
+    PitchBend,
+    ChannelPressure,
+    PolyPressure,
+    Nrpn,
+    Rpn,
+    ControlChange14,
+    BankProgramChange,
+    SongPosition,
+    Tempo,
+    Start,
+    Stop,
+    Continue,
+    Delay,
     Empty,
 }
 
@@ -196,8 +398,25 @@ impl MessageType {
     fn all() -> Vec<MessageType> {
         vec![
             MessageType::Empty,
+            MessageType::Delay,
             MessageType::ProgramChange,
             MessageType::ControlChange,
+            MessageType::NoteOn,
+            MessageType::NoteOff,
+            MessageType::SysEx,
+            MessageType::Fractal,
+            MessageType::PitchBend,
+            MessageType::ChannelPressure,
+            MessageType::PolyPressure,
+            MessageType::Nrpn,
+            MessageType::Rpn,
+            MessageType::ControlChange14,
+            MessageType::BankProgramChange,
+            MessageType::SongPosition,
+            MessageType::Tempo,
+            MessageType::Start,
+            MessageType::Stop,
+            MessageType::Continue,
         ]
     }
 }
@@ -207,6 +426,29 @@ impl From<&MessageType> for MidiMessage {
         match message_type {
             MessageType::ProgramChange => MidiMessage::ProgramChange(ProgramChange::default()),
             MessageType::ControlChange => MidiMessage::ControlChange(ControlChange::default()),
+            MessageType::NoteOn => MidiMessage::NoteOn(NoteOn::default()),
+            MessageType::NoteOff => MidiMessage::NoteOff(NoteOff::default()),
+            MessageType::SysEx => MidiMessage::SysEx(SysEx::default()),
+            MessageType::Fractal => MidiMessage::Fractal(FractalMessage::default()),
+            MessageType::PitchBend => MidiMessage::PitchBend(PitchBend::default()),
+            MessageType::ChannelPressure => {
+                MidiMessage::ChannelPressure(ChannelPressure::default())
+            }
+            MessageType::PolyPressure => MidiMessage::PolyPressure(PolyPressure::default()),
+            MessageType::Nrpn => MidiMessage::Nrpn(Nrpn::default()),
+            MessageType::Rpn => MidiMessage::Rpn(Rpn::default()),
+            MessageType::ControlChange14 => {
+                MidiMessage::ControlChange14(ControlChange14::default())
+            }
+            MessageType::BankProgramChange => {
+                MidiMessage::BankProgramChange(BankProgramChange::default())
+            }
+            MessageType::SongPosition => MidiMessage::SongPosition(SongPosition::default()),
+            MessageType::Tempo => MidiMessage::Tempo(clock::DEFAULT_BPM),
+            MessageType::Start => MidiMessage::Start,
+            MessageType::Stop => MidiMessage::Stop,
+            MessageType::Continue => MidiMessage::Continue,
+            MessageType::Delay => MidiMessage::Delay(0),
             MessageType::Empty => MidiMessage::Empty,
         }
     }
@@ -217,6 +459,23 @@ impl From<&str> for MessageType {
         match message {
             "Program Change" => MessageType::ProgramChange,
             "Control Change" => MessageType::ControlChange,
+            "Note On" => MessageType::NoteOn,
+            "Note Off" => MessageType::NoteOff,
+            "SysEx" => MessageType::SysEx,
+            "Fractal SysEx" => MessageType::Fractal,
+            "Pitch Bend" => MessageType::PitchBend,
+            "Channel Pressure" => MessageType::ChannelPressure,
+            "Poly Pressure" => MessageType::PolyPressure,
+            "NRPN" => MessageType::Nrpn,
+            "RPN" => MessageType::Rpn,
+            "Control Change (14-bit)" => MessageType::ControlChange14,
+            "Bank + Program Change" => MessageType::BankProgramChange,
+            "Song Position" => MessageType::SongPosition,
+            "Clock Tempo" => MessageType::Tempo,
+            "Clock Start" => MessageType::Start,
+            "Clock Stop" => MessageType::Stop,
+            "Clock Continue" => MessageType::Continue,
+            "Delay" => MessageType::Delay,
             _ => MessageType::Empty,
         }
     }
@@ -227,6 +486,23 @@ impl From<MidiMessage> for MessageType {
         match message {
             MidiMessage::ProgramChange(_) => MessageType::ProgramChange,
             MidiMessage::ControlChange(_) => MessageType::ControlChange,
+            MidiMessage::NoteOn(_) => MessageType::NoteOn,
+            MidiMessage::NoteOff(_) => MessageType::NoteOff,
+            MidiMessage::SysEx(_) => MessageType::SysEx,
+            MidiMessage::Fractal(_) => MessageType::Fractal,
+            MidiMessage::PitchBend(_) => MessageType::PitchBend,
+            MidiMessage::ChannelPressure(_) => MessageType::ChannelPressure,
+            MidiMessage::PolyPressure(_) => MessageType::PolyPressure,
+            MidiMessage::Nrpn(_) => MessageType::Nrpn,
+            MidiMessage::Rpn(_) => MessageType::Rpn,
+            MidiMessage::ControlChange14(_) => MessageType::ControlChange14,
+            MidiMessage::BankProgramChange(_) => MessageType::BankProgramChange,
+            MidiMessage::SongPosition(_) => MessageType::SongPosition,
+            MidiMessage::Tempo(_) => MessageType::Tempo,
+            MidiMessage::Start => MessageType::Start,
+            MidiMessage::Stop => MessageType::Stop,
+            MidiMessage::Continue => MessageType::Continue,
+            MidiMessage::Delay(_) => MessageType::Delay,
             _ => MessageType::Empty,
         }
     }
@@ -237,6 +513,23 @@ impl Display for MessageType {
         match self {
             MessageType::ProgramChange => write!(f, "Program Change"),
             MessageType::ControlChange => write!(f, "Control Change"),
+            MessageType::NoteOn => write!(f, "Note On"),
+            MessageType::NoteOff => write!(f, "Note Off"),
+            MessageType::SysEx => write!(f, "SysEx"),
+            MessageType::Fractal => write!(f, "Fractal SysEx"),
+            MessageType::PitchBend => write!(f, "Pitch Bend"),
+            MessageType::ChannelPressure => write!(f, "Channel Pressure"),
+            MessageType::PolyPressure => write!(f, "Poly Pressure"),
+            MessageType::Nrpn => write!(f, "NRPN"),
+            MessageType::Rpn => write!(f, "RPN"),
+            MessageType::ControlChange14 => write!(f, "Control Change (14-bit)"),
+            MessageType::BankProgramChange => write!(f, "Bank + Program Change"),
+            MessageType::SongPosition => write!(f, "Song Position"),
+            MessageType::Tempo => write!(f, "Clock Tempo"),
+            MessageType::Start => write!(f, "Clock Start"),
+            MessageType::Stop => write!(f, "Clock Stop"),
+            MessageType::Continue => write!(f, "Clock Continue"),
+            MessageType::Delay => write!(f, "Delay"),
             MessageType::Empty => write!(f, "Empty"),
         }
     }
@@ -312,9 +605,19 @@ fn MessageView<'a>(
     cx: Scope<'a>,
     index: usize,
     current_value: MidiMessage,
+    #[props(!optional)] device: Option<DeviceRef>,
+    outputs: Vec<String>,
+    learning: bool,
+    on_learn: EventHandler<'a>,
     on_change: EventHandler<'a, MidiMessage>,
+    on_device_change: EventHandler<'a, Option<DeviceRef>>,
 ) -> Element {
     let message_type: &UseState<MessageType> = use_state(cx, || current_value.clone().into());
+    // The message can also be replaced from outside, e.g. by MIDI learn.
+    use_effect(cx, (current_value,), |(current_value,)| {
+        let message_type = message_type.clone();
+        async move { message_type.set(current_value.into()) }
+    });
     cx.render(rsx!(
         div { class: "py-1",
             div { class: "flex",
@@ -333,16 +636,106 @@ fn MessageView<'a>(
                         on_change.call(MidiMessage::Empty);
                     }
                 }
+                LearnButton { learning: *learning, on_click: move |_| on_learn.call(()) }
+                MessageDeviceView {
+                    current: device.clone(),
+                    outputs: outputs.clone(),
+                    on_change: move |device: Option<DeviceRef>| on_device_change.call(device)
+                }
             }
             div { class: "mx-4",
                 match current_value {
                     MidiMessage::ProgramChange(pc) => rsx!(ProgramChangeView {
                         current_value: pc.clone(),
-                        on_change: move |pc: ProgramChange| on_change.call(MidiMessage::ProgramChange(pc))
+                        on_change: move |pc: ProgramChange| {
+                            on_change.call(MidiMessage::ProgramChange(pc))
+                        }
                     }),
                     MidiMessage::ControlChange(cc) => rsx!(ControlChangeView {
                         current_value: cc.clone(),
-                        on_change: move |cc: ControlChange| on_change.call(MidiMessage::ControlChange(cc))
+                        on_change: move |cc: ControlChange| {
+                            on_change.call(MidiMessage::ControlChange(cc))
+                        }
+                    }),
+                    MidiMessage::NoteOn(note) => rsx!(NoteOnView {
+                        current_value: note.clone(),
+                        on_change: move |note: NoteOn| on_change.call(MidiMessage::NoteOn(note))
+                    }),
+                    MidiMessage::NoteOff(note) => rsx!(NoteOffView {
+                        current_value: note.clone(),
+                        on_change: move |note: NoteOff| on_change.call(MidiMessage::NoteOff(note))
+                    }),
+                    MidiMessage::SysEx(sysex) => rsx!(SysExView {
+                        current_value: sysex.clone(),
+                        on_change: move |sysex: SysEx| on_change.call(MidiMessage::SysEx(sysex))
+                    }),
+                    MidiMessage::Fractal(fractal) => rsx!(FractalView {
+                        current_value: fractal.clone(),
+                        on_change: move |fractal: FractalMessage| {
+                            on_change.call(MidiMessage::Fractal(fractal))
+                        }
+                    }),
+                    MidiMessage::PitchBend(message) => rsx!(PitchBendView {
+                        current_value: message.clone(),
+                        on_change: move |message: PitchBend| {
+                            on_change.call(MidiMessage::PitchBend(message))
+                        }
+                    }),
+                    MidiMessage::ChannelPressure(message) => rsx!(ChannelPressureView {
+                        current_value: message.clone(),
+                        on_change: move |message: ChannelPressure| {
+                            on_change.call(MidiMessage::ChannelPressure(message))
+                        }
+                    }),
+                    MidiMessage::PolyPressure(message) => rsx!(PolyPressureView {
+                        current_value: message.clone(),
+                        on_change: move |message: PolyPressure| {
+                            on_change.call(MidiMessage::PolyPressure(message))
+                        }
+                    }),
+                    MidiMessage::Nrpn(message) => rsx!(NrpnView {
+                        current_value: message.clone(),
+                        on_change: move |message: Nrpn| on_change.call(MidiMessage::Nrpn(message))
+                    }),
+                    MidiMessage::Rpn(message) => rsx!(RpnView {
+                        current_value: message.clone(),
+                        on_change: move |message: Rpn| on_change.call(MidiMessage::Rpn(message))
+                    }),
+                    MidiMessage::ControlChange14(message) => rsx!(ControlChange14View {
+                        current_value: message.clone(),
+                        on_change: move |message: ControlChange14| {
+                            on_change.call(MidiMessage::ControlChange14(message))
+                        }
+                    }),
+                    MidiMessage::BankProgramChange(message) => rsx!(BankProgramChangeView {
+                        current_value: message.clone(),
+                        on_change: move |message: BankProgramChange| {
+                            on_change.call(MidiMessage::BankProgramChange(message))
+                        }
+                    }),
+                    MidiMessage::SongPosition(message) => rsx!(SongPositionView {
+                        current_value: message.clone(),
+                        on_change: move |message: SongPosition| {
+                            on_change.call(MidiMessage::SongPosition(message))
+                        }
+                    }),
+                    MidiMessage::Tempo(bpm) => rsx!(div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-teal-500 bg-grey-100",
+                        NumberInputView {
+                            min_value: clock::MIN_BPM as i32,
+                            max_value: clock::MAX_BPM as i32,
+                            current_value: *bpm as i32,
+                            label: "Tempo (BPM)".to_string(),
+                            on_change: move |i: i32| on_change.call(MidiMessage::Tempo(i as u16))
+                        }
+                    }),
+                    MidiMessage::Delay(delay) => rsx!(div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-teal-500 bg-grey-100",
+                        NumberInputView {
+                            min_value: 0,
+                            max_value: i32::MAX,
+                            current_value: i32::try_from(*delay).unwrap_or(i32::MAX),
+                            label: "Delay (ms)".to_string(),
+                            on_change: move |i: i32| on_change.call(MidiMessage::Delay(i as u64))
+                        }
                     }),
                     _ => rsx!({}),
                 }
@@ -351,6 +744,125 @@ fn MessageView<'a>(
     ))
 }
 
+#[component]
+fn DeviceView<'a>(
+    cx: Scope<'a>,
+    current: DeviceRef,
+    outputs: Vec<String>,
+    on_change: EventHandler<'a, DeviceRef>,
+) -> Element<'a> {
+    let missing = !current.is_available(outputs);
+    cx.render(rsx!(
+        div { class: "flex",
+            div { class: "relative",
+                span { class: "absolute pl-3", Badge { text: "Device".to_string() } }
+            }
+            select {
+                class: "peer flex-1 rounded-md border-2 min-w-32 px-5 py-2 mx-2 mt-4 md:px-5 text-end align-text-bottom",
+                onchange: move |e| on_change.call(DeviceRef::from(e.value.to_string())),
+                if missing {
+                    rsx!(option { selected: true, disabled: true, "{current} (missing)" })
+                }
+                for name in outputs.iter() {
+                    option { selected: current.port_name().as_ref() == Some(name), value: "{name}", "{name}" }
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn DeviceAliasInput<'a>(
+    cx: Scope<'a>,
+    value: String,
+    on_change: EventHandler<'a, String>,
+) -> Element<'a> {
+    cx.render(rsx!(input {
+        class: "border-2 text-sm rounded-md p-2 mt-4 mr-2",
+        r#type: "text",
+        placeholder: "Device Alias",
+        onchange: move |e| on_change.call(e.value.to_string()),
+        value: "{value}"
+    }))
+}
+
+/// Lets a message go to another output than the rest of its preset.
+#[component]
+fn MessageDeviceView<'a>(
+    cx: Scope<'a>,
+    #[props(!optional)] current: Option<DeviceRef>,
+    outputs: Vec<String>,
+    on_change: EventHandler<'a, Option<DeviceRef>>,
+) -> Element<'a> {
+    let missing = current
+        .as_ref()
+        .filter(|device| !device.is_available(outputs));
+    cx.render(rsx!(
+        select {
+            class: "flex rounded-md border-2 px-2 ml-2",
+            onchange: move |e| {
+                on_change.call(Some(e.value.to_string()).filter(|name| !name.is_empty()).map(DeviceRef::from))
+            },
+            option { selected: current.is_none(), value: "", "Preset Device" }
+            if let Some(device) = missing {
+                rsx!(option { selected: true, disabled: true, "{device} (missing)" })
+            }
+            for name in outputs.iter() {
+                option {
+                    selected: current.as_ref().and_then(DeviceRef::port_name).as_ref() == Some(name),
+                    value: "{name}",
+                    "{name}"
+                }
+            }
+        }
+    ))
+}
+
+/// Picks the input port MIDI learn listens on.
+#[component]
+fn LearnInputView<'a>(
+    cx: Scope<'a>,
+    #[props(!optional)] current: Option<String>,
+    inputs: Vec<String>,
+    on_change: EventHandler<'a, String>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex ml-2",
+            div { class: "relative",
+                span { class: "absolute pl-3", Badge { text: "Learn From".to_string() } }
+            }
+            select {
+                class: "peer flex-1 rounded-md border-2 min-w-32 px-5 py-2 mx-2 mt-4 md:px-5 text-end align-text-bottom",
+                onchange: move |e| on_change.call(e.value.to_string()),
+                if inputs.is_empty() {
+                    rsx!(option { selected: true, disabled: true, "No MIDI inputs" })
+                }
+                for name in inputs.iter() {
+                    option { selected: current.as_ref() == Some(name), value: "{name}", "{name}" }
+                }
+            }
+        }
+    ))
+}
+
+/// Fills in the message from the next one received on the learn input.
+#[component]
+fn LearnButton<'a>(cx: Scope<'a>, learning: bool, on_click: EventHandler<'a>) -> Element<'a> {
+    let styling = if *learning {
+        "bg-amber-200 hover:bg-amber-300 border-amber-500 animate-pulse"
+    } else {
+        "hover:bg-slate-200"
+    };
+    cx.render(rsx!(
+        button {
+            class: "flex-auto border-2 rounded-md items-center px-2 ml-2 transition md:px-5 {styling}",
+            onclick: move |_| on_click.call(()),
+            span { class: "fas fa-satellite-dish pr-2" }
+            if *learning { "Listening..." } else { "Learn" }
+        }
+    ))
+}
+
 #[component]
 fn ClearMessageButton<'a>(cx: Scope<'a>, on_click: EventHandler<'a>) -> Element {
     cx.render(rsx!(
@@ -398,30 +910,17 @@ fn ProgramChangeView<'a>(
 ) -> Element {
     cx.render(rsx!(
         div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-indigo-500 bg-grey-100",
-            NumberView {
-                min_value: 1,
-                max_value: 16,
-                current_value: current_value.channel as i32,
-                label: "MIDI Channel".to_string(),
-                on_change: move |i: i32| {
-                    on_change
-                        .call(ProgramChange {
-                            channel: i as u8,
-                            program: current_value.program,
-                        })
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(ProgramChange { channel, ..current_value.clone() })
                 }
             }
-            NumberView {
-                min_value: 0,
-                max_value: 127,
-                current_value: current_value.program as i32,
+            DataView {
                 label: "PC Number".to_string(),
-                on_change: move |i: i32| {
-                    on_change
-                        .call(ProgramChange {
-                            channel: current_value.channel,
-                            program: i as u8,
-                        })
+                current_value: current_value.program,
+                on_change: move |program: U7| {
+                    on_change.call(ProgramChange { program, ..current_value.clone() })
                 }
             }
         }
@@ -429,7 +928,7 @@ fn ProgramChangeView<'a>(
 }
 
 #[component]
-fn Badge(cx: Scope, text: String) -> Element {
+pub fn Badge(cx: Scope, text: String) -> Element {
     cx.render(rsx!(
         span { class: "inline-flex text-nowrap min-w-full items-center rounded-md bg-gray-50 px-2 py-1 text-xs font-medium text-gray-600 ring-1 ring-inset ring-gray-500/10",
             text.to_string()
@@ -498,52 +997,608 @@ fn NumberView<'a>(
 
 #[component]
 fn ControlChangeView<'a>(
-    cx: Scope,
+    cx: Scope<'a>,
     current_value: ControlChange,
     on_change: EventHandler<'a, ControlChange>,
 ) -> Element {
     cx.render(rsx!(
         div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-rose-500 bg-grey-100",
-            NumberView {
-                min_value: 1,
-                max_value: 16,
-                current_value: current_value.channel as i32,
-                label: "MIDI Channel".to_string(),
-                on_change: move |i: i32| {
-                    on_change
-                        .call(ControlChange {
-                            channel: i as u8,
-                            control_number: current_value.control_number,
-                            value: current_value.value,
-                        })
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(ControlChange { channel, ..current_value.clone() })
                 }
             }
-            NumberView {
-                min_value: 0,
-                max_value: 127,
-                current_value: current_value.control_number as i32,
+            DataView {
                 label: "CC Number".to_string(),
-                on_change: move |i: i32| {
-                    on_change
-                        .call(ControlChange {
-                            channel: current_value.channel,
-                            control_number: i as u8,
-                            value: current_value.value,
-                        })
+                current_value: current_value.control_number,
+                on_change: move |control_number: U7| {
+                    on_change.call(ControlChange { control_number, ..current_value.clone() })
+                }
+            }
+            DataView {
+                label: "CC Value".to_string(),
+                current_value: current_value.value,
+                on_change: move |value: U7| {
+                    on_change.call(ControlChange { value, ..current_value.clone() })
+                }
+            }
+        }
+    ))
+}
+
+fn note_labels() -> Vec<NumberLabel<u8>> {
+    (0..=127u8)
+        .map(|note| NumberLabel {
+            label: format!("{} ({})", note_name(note), note),
+            value: note,
+        })
+        .collect()
+}
+
+#[component]
+fn ChannelView<'a>(
+    cx: Scope<'a>,
+    current_value: MidiChannel,
+    on_change: EventHandler<'a, MidiChannel>,
+) -> Element<'a> {
+    cx.render(rsx!(NumberView {
+        min_value: MidiChannel::FIRST as i32,
+        max_value: MidiChannel::LAST as i32,
+        current_value: current_value.number() as i32,
+        label: "MIDI Channel".to_string(),
+        on_change: move |i: i32| {
+            if let Ok(channel) = MidiChannel::from_number(i) {
+                on_change.call(channel)
+            }
+        }
+    }))
+}
+
+#[component]
+pub fn DataView<'a>(
+    cx: Scope<'a>,
+    label: String,
+    current_value: U7,
+    on_change: EventHandler<'a, U7>,
+) -> Element<'a> {
+    cx.render(rsx!(NumberView {
+        min_value: 0,
+        max_value: U7::MAX as i32,
+        current_value: current_value.get() as i32,
+        label: label.to_string(),
+        on_change: move |i: i32| {
+            if let Ok(value) = U7::try_from(i) {
+                on_change.call(value)
+            }
+        }
+    }))
+}
+
+#[component]
+fn Data14View<'a>(
+    cx: Scope<'a>,
+    label: String,
+    current_value: U14,
+    on_change: EventHandler<'a, U14>,
+) -> Element<'a> {
+    cx.render(rsx!(NumberInputView {
+        min_value: 0,
+        max_value: U14::MAX as i32,
+        current_value: current_value.get() as i32,
+        label: label.to_string(),
+        on_change: move |i: i32| {
+            if let Ok(value) = U14::try_from(i) {
+                on_change.call(value)
+            }
+        }
+    }))
+}
+
+#[component]
+fn NoteView<'a>(cx: Scope<'a>, current_value: U7, on_change: EventHandler<'a, U7>) -> Element<'a> {
+    cx.render(rsx!(LabeledNumberView {
+        values: note_labels(),
+        label: "Note".to_string(),
+        current_value: current_value.get(),
+        on_change: move |note: u8| {
+            if let Ok(note) = U7::new(note) {
+                on_change.call(note)
+            }
+        }
+    }))
+}
+
+#[component]
+fn NoteOnView<'a>(
+    cx: Scope<'a>,
+    current_value: NoteOn,
+    on_change: EventHandler<'a, NoteOn>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-emerald-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(NoteOn { channel, ..current_value.clone() })
+                }
+            }
+            NoteView {
+                current_value: current_value.note,
+                on_change: move |note: U7| on_change.call(NoteOn { note, ..current_value.clone() })
+            }
+            DataView {
+                label: "Velocity".to_string(),
+                current_value: current_value.velocity,
+                on_change: move |velocity: U7| {
+                    on_change.call(NoteOn { velocity, ..current_value.clone() })
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn NoteOffView<'a>(
+    cx: Scope<'a>,
+    current_value: NoteOff,
+    on_change: EventHandler<'a, NoteOff>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-amber-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(NoteOff { channel, ..current_value.clone() })
+                }
+            }
+            NoteView {
+                current_value: current_value.note,
+                on_change: move |note: U7| on_change.call(NoteOff { note, ..current_value.clone() })
+            }
+            DataView {
+                label: "Release Velocity".to_string(),
+                current_value: current_value.velocity,
+                on_change: move |velocity: U7| {
+                    on_change.call(NoteOff { velocity, ..current_value.clone() })
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn SysExView<'a>(
+    cx: Scope<'a>,
+    current_value: SysEx,
+    on_change: EventHandler<'a, SysEx>,
+) -> Element<'a> {
+    let text = use_state(cx, || current_value.to_hex());
+    let error: &UseState<Option<String>> = use_state(cx, || None);
+    cx.render(rsx!(
+        div { class: "flex flex-col my-1 p-1 border-2 rounded-md border-l-4 border-l-sky-500 bg-grey-100",
+            div { class: "relative",
+                span { class: "absolute pl-3", Badge { text: "SysEx (hex)".to_string() } }
+            }
+            input {
+                class: "flex-1 rounded-md border-2 px-5 py-2 mx-2 mt-4 font-mono",
+                r#type: "text",
+                placeholder: "F0 00 01 74 F7",
+                value: "{text}",
+                oninput: move |e| {
+                    let value = e.value.to_string();
+                    match SysEx::from_hex(value.as_str()) {
+                        Ok(sysex) => {
+                            error.set(None);
+                            on_change.call(sysex);
+                        }
+                        Err(err) => error.set(Some(err.to_string())),
+                    }
+                    text.set(value);
+                }
+            }
+            if let Some(err) = error.get() {
+                rsx!(span { class: "mx-2 mt-1 text-xs text-red-600", "{err}" })
+            } else if let Ok(fractal) = FractalMessage::parse(&current_value.data) {
+                rsx!(span { class: "mx-2 mt-1 text-xs text-gray-600", "{fractal}" })
+            }
+        }
+    ))
+}
+
+#[component]
+fn NumberInputView<'a>(
+    cx: Scope<'a>,
+    min_value: i32,
+    max_value: i32,
+    label: String,
+    current_value: i32,
+    on_change: EventHandler<'a, i32>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "relative",
+            span { class: "absolute pl-3", Badge { text: label.to_string() } }
+        }
+        input {
+            class: "peer flex-1 rounded-md border-2 w-28 px-5 py-2 mx-2 mt-4 md:px-5 text-end align-text-bottom",
+            r#type: "number",
+            min: "{min_value}",
+            max: "{max_value}",
+            value: "{current_value}",
+            onchange: move |e| {
+                if let Ok(i) = e.value.parse::<i32>() {
+                    on_change.call(i.clamp(*min_value, *max_value));
+                }
+            }
+        }
+    ))
+}
+
+fn fractal_commands() -> Vec<FractalCommand> {
+    vec![
+        FractalCommand::Scene(0),
+        FractalCommand::Bypass {
+            block: 0,
+            bypassed: true,
+        },
+        FractalCommand::Channel {
+            block: 0,
+            channel: 0,
+        },
+        FractalCommand::Tempo(120),
+        FractalCommand::TapTempo,
+        FractalCommand::Tuner(true),
+        FractalCommand::QueryPreset(None),
+        FractalCommand::QueryScene,
+        FractalCommand::QueryTempo,
+    ]
+}
+
+fn fractal_command_name(command: &FractalCommand) -> &'static str {
+    match command {
+        FractalCommand::QueryPreset(_) => "Query Preset",
+        FractalCommand::Preset { .. } => "Preset",
+        FractalCommand::QueryScene => "Query Scene",
+        FractalCommand::Scene(_) => "Set Scene",
+        FractalCommand::QueryBypass { .. } => "Query Bypass",
+        FractalCommand::Bypass { .. } => "Set Bypass",
+        FractalCommand::QueryChannel { .. } => "Query Channel",
+        FractalCommand::Channel { .. } => "Set Channel",
+        FractalCommand::QueryTempo => "Query Tempo",
+        FractalCommand::Tempo(_) => "Set Tempo",
+        FractalCommand::TapTempo => "Tap Tempo",
+        FractalCommand::Tuner(_) => "Tuner",
+        FractalCommand::TunerInfo { .. } => "Tuner Info",
+    }
+}
+
+#[component]
+fn FractalView<'a>(
+    cx: Scope<'a>,
+    current_value: FractalMessage,
+    on_change: EventHandler<'a, FractalMessage>,
+) -> Element<'a> {
+    let model = current_value.model;
+    let command = current_value.command.clone();
+    let update = move |command: FractalCommand| on_change.call(FractalMessage { model, command });
+    let commands = fractal_commands();
+    let current_name = fractal_command_name(&command);
+
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-violet-500 bg-grey-100",
+            LabeledNumberView {
+                values: enum_iterator::all::<FractalModel>()
+                    .map(|m| NumberLabel {
+                        label: m.to_string(),
+                        value: m as u8,
+                    })
+                    .collect(),
+                label: "Model".to_string(),
+                current_value: model as u8,
+                on_change: move |id: u8| {
+                    if let Ok(model) = FractalModel::try_from(id) {
+                        on_change.call(FractalMessage { model, command: current_value.command.clone() })
+                    }
+                }
+            }
+            LabeledNumberView {
+                values: commands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| NumberLabel {
+                        label: fractal_command_name(c).to_string(),
+                        value: i,
+                    })
+                    .collect(),
+                label: "Function".to_string(),
+                current_value: commands
+                    .iter()
+                    .position(|c| fractal_command_name(c) == current_name)
+                    .unwrap_or(0),
+                on_change: move |i: usize| update(fractal_commands()[i].clone())
+            }
+            match command {
+                FractalCommand::Scene(scene) => rsx!(NumberView {
+                    min_value: 1,
+                    max_value: 8,
+                    current_value: scene as i32 + 1,
+                    label: "Scene".to_string(),
+                    on_change: move |i: i32| update(FractalCommand::Scene((i - 1) as u8))
+                }),
+                FractalCommand::Bypass { block, bypassed } => rsx!(
+                    NumberInputView {
+                        min_value: 0,
+                        max_value: 16383,
+                        current_value: block as i32,
+                        label: "Block ID".to_string(),
+                        on_change: move |i: i32| update(FractalCommand::Bypass { block: i as u16, bypassed })
+                    }
+                    LabeledNumberView {
+                        values: vec![
+                            NumberLabel { label: "Engaged".to_string(), value: false },
+                            NumberLabel { label: "Bypassed".to_string(), value: true },
+                        ],
+                        label: "State".to_string(),
+                        current_value: bypassed,
+                        on_change: move |bypassed: bool| update(FractalCommand::Bypass { block, bypassed })
+                    }
+                ),
+                FractalCommand::Channel { block, channel } => rsx!(
+                    NumberInputView {
+                        min_value: 0,
+                        max_value: 16383,
+                        current_value: block as i32,
+                        label: "Block ID".to_string(),
+                        on_change: move |i: i32| update(FractalCommand::Channel { block: i as u16, channel })
+                    }
+                    LabeledNumberView {
+                        values: (0..4u8)
+                            .map(|c| NumberLabel { label: ((b'A' + c) as char).to_string(), value: c })
+                            .collect(),
+                        label: "Channel".to_string(),
+                        current_value: channel,
+                        on_change: move |channel: u8| update(FractalCommand::Channel { block, channel })
+                    }
+                ),
+                FractalCommand::Tempo(bpm) => rsx!(NumberInputView {
+                    min_value: 24,
+                    max_value: 250,
+                    current_value: bpm as i32,
+                    label: "Tempo (BPM)".to_string(),
+                    on_change: move |i: i32| update(FractalCommand::Tempo(i as u16))
+                }),
+                FractalCommand::Tuner(on) => rsx!(LabeledNumberView {
+                    values: vec![
+                        NumberLabel { label: "Off".to_string(), value: false },
+                        NumberLabel { label: "On".to_string(), value: true },
+                    ],
+                    label: "Tuner".to_string(),
+                    current_value: on,
+                    on_change: move |on: bool| update(FractalCommand::Tuner(on))
+                }),
+                _ => rsx!({}),
+            }
+        }
+    ))
+}
+
+#[component]
+fn PitchBendView<'a>(
+    cx: Scope<'a>,
+    current_value: PitchBend,
+    on_change: EventHandler<'a, PitchBend>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-cyan-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(PitchBend { channel, ..current_value.clone() })
+                }
+            }
+            Data14View {
+                label: "Bend (8192 = centre)".to_string(),
+                current_value: current_value.value,
+                on_change: move |value: U14| {
+                    on_change.call(PitchBend { value, ..current_value.clone() })
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn ChannelPressureView<'a>(
+    cx: Scope<'a>,
+    current_value: ChannelPressure,
+    on_change: EventHandler<'a, ChannelPressure>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-lime-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(ChannelPressure { channel, ..current_value.clone() })
+                }
+            }
+            DataView {
+                label: "Pressure".to_string(),
+                current_value: current_value.pressure,
+                on_change: move |pressure: U7| {
+                    on_change.call(ChannelPressure { pressure, ..current_value.clone() })
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn PolyPressureView<'a>(
+    cx: Scope<'a>,
+    current_value: PolyPressure,
+    on_change: EventHandler<'a, PolyPressure>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-fuchsia-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(PolyPressure { channel, ..current_value.clone() })
+                }
+            }
+            NoteView {
+                current_value: current_value.note,
+                on_change: move |note: U7| {
+                    on_change.call(PolyPressure { note, ..current_value.clone() })
+                }
+            }
+            DataView {
+                label: "Pressure".to_string(),
+                current_value: current_value.pressure,
+                on_change: move |pressure: U7| {
+                    on_change.call(PolyPressure { pressure, ..current_value.clone() })
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn NrpnView<'a>(
+    cx: Scope<'a>,
+    current_value: Nrpn,
+    on_change: EventHandler<'a, Nrpn>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-orange-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(Nrpn { channel, ..current_value.clone() })
+                }
+            }
+            Data14View {
+                label: "NRPN Parameter".to_string(),
+                current_value: current_value.parameter,
+                on_change: move |parameter: U14| {
+                    on_change.call(Nrpn { parameter, ..current_value.clone() })
+                }
+            }
+            Data14View {
+                label: "NRPN Value".to_string(),
+                current_value: current_value.value,
+                on_change: move |value: U14| on_change.call(Nrpn { value, ..current_value.clone() })
+            }
+        }
+    ))
+}
+
+#[component]
+fn RpnView<'a>(cx: Scope<'a>, current_value: Rpn, on_change: EventHandler<'a, Rpn>) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-yellow-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(Rpn { channel, ..current_value.clone() })
+                }
+            }
+            Data14View {
+                label: "RPN Parameter".to_string(),
+                current_value: current_value.parameter,
+                on_change: move |parameter: U14| {
+                    on_change.call(Rpn { parameter, ..current_value.clone() })
+                }
+            }
+            Data14View {
+                label: "RPN Value".to_string(),
+                current_value: current_value.value,
+                on_change: move |value: U14| on_change.call(Rpn { value, ..current_value.clone() })
+            }
+        }
+    ))
+}
+
+#[component]
+fn ControlChange14View<'a>(
+    cx: Scope<'a>,
+    current_value: ControlChange14,
+    on_change: EventHandler<'a, ControlChange14>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-pink-500 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(ControlChange14 { channel, ..current_value.clone() })
                 }
             }
             NumberView {
                 min_value: 0,
-                max_value: 127,
-                current_value: current_value.value as i32,
-                label: "CC Value".to_string(),
+                max_value: ControlChange14::MAX_CONTROL_NUMBER as i32,
+                current_value: current_value.control_number.get() as i32,
+                label: "CC Number (MSB)".to_string(),
                 on_change: move |i: i32| {
-                    on_change
-                        .call(ControlChange {
-                            channel: current_value.channel,
-                            control_number: current_value.control_number,
-                            value: i as u8,
-                        })
+                    let message = U7::try_from(i).and_then(|control_number| {
+                        ControlChange14::new(current_value.channel, control_number, current_value.value)
+                    });
+                    if let Ok(message) = message {
+                        on_change.call(message)
+                    }
+                }
+            }
+            Data14View {
+                label: "CC Value".to_string(),
+                current_value: current_value.value,
+                on_change: move |value: U14| {
+                    on_change.call(ControlChange14 { value, ..current_value.clone() })
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn BankProgramChangeView<'a>(
+    cx: Scope<'a>,
+    current_value: BankProgramChange,
+    on_change: EventHandler<'a, BankProgramChange>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-indigo-700 bg-grey-100",
+            ChannelView {
+                current_value: current_value.channel,
+                on_change: move |channel: MidiChannel| {
+                    on_change.call(BankProgramChange { channel, ..current_value.clone() })
+                }
+            }
+            Data14View {
+                label: "Preset Number".to_string(),
+                current_value: current_value.preset,
+                on_change: move |preset: U14| {
+                    on_change.call(BankProgramChange { preset, ..current_value.clone() })
+                }
+            }
+            span { class: "flex items-center mx-2 mt-4 text-xs text-gray-600",
+                "Bank {current_value.bank()}, Program {current_value.program()}"
+            }
+        }
+    ))
+}
+
+#[component]
+fn SongPositionView<'a>(
+    cx: Scope<'a>,
+    current_value: SongPosition,
+    on_change: EventHandler<'a, SongPosition>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex my-1 p-1 border-2 rounded-md border-l-4 border-l-teal-500 bg-grey-100",
+            Data14View {
+                label: "Position (16ths)".to_string(),
+                current_value: current_value.position,
+                on_change: move |position: U14| {
+                    on_change.call(SongPosition { position })
                 }
             }
         }
@@ -0,0 +1,284 @@
+use dioxus::prelude::*;
+use dioxus_router::prelude::use_navigator;
+
+use crate::components::data::{use_persistent, AppData};
+use crate::components::devices::{open_inputs, use_inputs, use_outputs};
+use crate::components::preset::{Button, DataView};
+use crate::components::translator::LabeledSelect;
+use crate::midi::channel::MidiChannel;
+use crate::midi::input;
+use crate::midi::routing;
+use crate::midi::routing::{CcRemap, RouteKind, ThruRoute, ValueScale};
+use crate::midi::value::U7;
+use crate::Route;
+
+/// Forwards messages along the stored routes for as long as the app is open, and keeps their
+/// inputs open as they come and go.
+pub fn use_routing(cx: &ScopeState) {
+    let inputs = use_inputs(cx);
+    cx.use_hook(|| {
+        routing::set_routes(AppData::stored().routes);
+        input::listen(routing::forward)
+    });
+    use_effect(cx, (&inputs,), |(inputs,)| async move {
+        let data = AppData::stored();
+        open_inputs(data.routes.iter().map(|route| &route.input), &inputs);
+    });
+}
+
+#[component]
+pub fn RoutingView(cx: Scope) -> Element {
+    let data = use_persistent(cx, "app_data", AppData::default);
+    let nav = use_navigator(cx);
+    let inputs = use_inputs(cx);
+    let outputs = use_outputs(cx);
+    let new_route = ThruRoute {
+        input: inputs.first().cloned().unwrap_or_default(),
+        output: outputs.first().cloned().unwrap_or_default(),
+        ..ThruRoute::default()
+    };
+
+    let save = move |routes: Vec<ThruRoute>| {
+        routing::set_routes(routes.clone());
+        data.set(AppData {
+            routes,
+            ..data.get()
+        });
+    };
+
+    cx.render(rsx!(
+        div { class: "container bg-slate-50 mx-auto px-2 pt-2 text-sm mb-4",
+            div { class: "flex",
+                Button {
+                    styling: "",
+                    text: "Back to Dashboard",
+                    icon: "fas fa-rotate-back",
+                    on_click: move |_| {
+                        nav.push(Route::DashboardView {});
+                    }
+                }
+                Button {
+                    styling: "bg-teal-300 hover:bg-teal-500 border-teal-500",
+                    text: "Add Route",
+                    icon: "fas fa-plus",
+                    on_click: move |_| {
+                        let mut routes = data.get().routes;
+                        routes.push(new_route.clone());
+                        save(routes);
+                    }
+                }
+            }
+            for (i , route) in data.get().routes.into_iter().enumerate() {
+                ThruRouteView {
+                    index: i,
+                    route: route,
+                    inputs: inputs.clone(),
+                    outputs: outputs.clone(),
+                    on_change: move |route: ThruRoute| {
+                        if let Err(err) = input::open(&route.input) {
+                            log::error!("Error opening MIDI input {}: {}", route.input, err);
+                        }
+                        let mut routes = data.get().routes;
+                        routes[i] = route;
+                        save(routes);
+                    },
+                    on_delete: move |_| {
+                        let mut routes = data.get().routes;
+                        routes.remove(i);
+                        save(routes);
+                    }
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn ThruRouteView<'a>(
+    cx: Scope<'a>,
+    index: usize,
+    route: ThruRoute,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    on_change: EventHandler<'a, ThruRoute>,
+    on_delete: EventHandler<'a>,
+) -> Element<'a> {
+    let kinds: Vec<(RouteKind, bool)> = enum_iterator::all::<RouteKind>()
+        .map(|kind| (kind, route.kinds.contains(&kind)))
+        .collect();
+    cx.render(rsx!(
+        div { class: "py-2 border-b",
+            div { class: "flex flex-wrap items-end",
+                span { class: "border-2 rounded-md flex items-center p-2 font-mono bg-grey-300", "{index}" }
+                PortSelect {
+                    label: "Input".to_string(),
+                    current: route.input.clone(),
+                    ports: inputs.clone(),
+                    on_change: move |input: String| on_change.call(ThruRoute { input, ..route.clone() })
+                }
+                span { class: "flex items-center px-2 py-2", span { class: "fas fa-arrow-right" } }
+                PortSelect {
+                    label: "Output".to_string(),
+                    current: route.output.clone(),
+                    ports: outputs.clone(),
+                    on_change: move |output: String| on_change.call(ThruRoute { output, ..route.clone() })
+                }
+                ChannelSelect {
+                    label: "Only Channel".to_string(),
+                    none_label: "Any".to_string(),
+                    current: route.channel,
+                    on_change: move |channel: Option<MidiChannel>| {
+                        on_change.call(ThruRoute { channel, ..route.clone() })
+                    }
+                }
+                ChannelSelect {
+                    label: "To Channel".to_string(),
+                    none_label: "Unchanged".to_string(),
+                    current: route.remap_channel,
+                    on_change: move |remap_channel: Option<MidiChannel>| {
+                        on_change.call(ThruRoute { remap_channel, ..route.clone() })
+                    }
+                }
+                DataView {
+                    label: "CC Value Min".to_string(),
+                    current_value: route.scale.min,
+                    on_change: move |min: U7| {
+                        let scale = ValueScale { min, ..route.scale };
+                        on_change.call(ThruRoute { scale, ..route.clone() })
+                    }
+                }
+                DataView {
+                    label: "CC Value Max".to_string(),
+                    current_value: route.scale.max,
+                    on_change: move |max: U7| {
+                        let scale = ValueScale { max, ..route.scale };
+                        on_change.call(ThruRoute { scale, ..route.clone() })
+                    }
+                }
+                Button {
+                    styling: "bg-red-300 hover:bg-red-500 border-red-500 ml-2",
+                    text: "Delete",
+                    icon: "fas fa-trash",
+                    on_click: move |_| on_delete.call(())
+                }
+            }
+            div { class: "flex flex-wrap gap-2 mt-2",
+                span { class: "flex items-center text-gray-600",
+                    if route.kinds.is_empty() { "Forwarding all types:" } else { "Forwarding:" }
+                }
+                for (kind , checked) in kinds.into_iter() {
+                    label { class: "flex items-center",
+                        input {
+                            r#type: "checkbox",
+                            class: "mr-1",
+                            checked: checked,
+                            onchange: move |_| {
+                                let mut kinds = route.kinds.clone();
+                                if checked {
+                                    kinds.retain(|k| *k != kind);
+                                } else {
+                                    kinds.push(kind);
+                                }
+                                on_change.call(ThruRoute { kinds, ..route.clone() })
+                            }
+                        }
+                        "{kind}"
+                    }
+                }
+            }
+            div { class: "flex flex-wrap items-end mt-2",
+                for (i , remap) in route.cc_remap.iter().copied().enumerate() {
+                    div { class: "flex items-end",
+                        DataView {
+                            label: "CC".to_string(),
+                            current_value: remap.from,
+                            on_change: move |from: U7| {
+                                let mut cc_remap = route.cc_remap.clone();
+                                cc_remap[i].from = from;
+                                on_change.call(ThruRoute { cc_remap, ..route.clone() })
+                            }
+                        }
+                        span { class: "flex items-center px-2 py-2", span { class: "fas fa-arrow-right" } }
+                        DataView {
+                            label: "As CC".to_string(),
+                            current_value: remap.to,
+                            on_change: move |to: U7| {
+                                let mut cc_remap = route.cc_remap.clone();
+                                cc_remap[i].to = to;
+                                on_change.call(ThruRoute { cc_remap, ..route.clone() })
+                            }
+                        }
+                        button {
+                            class: "border-2 rounded-md px-2 py-2 hover:bg-slate-200",
+                            onclick: move |_| {
+                                let mut cc_remap = route.cc_remap.clone();
+                                cc_remap.remove(i);
+                                on_change.call(ThruRoute { cc_remap, ..route.clone() })
+                            },
+                            span { class: "fas fa-xmark" }
+                        }
+                    }
+                }
+                Button {
+                    styling: "ml-2",
+                    text: "Add CC Remap",
+                    icon: "fas fa-plus",
+                    on_click: move |_| {
+                        let mut cc_remap = route.cc_remap.clone();
+                        cc_remap.push(CcRemap::default());
+                        on_change.call(ThruRoute { cc_remap, ..route.clone() })
+                    }
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn PortSelect<'a>(
+    cx: Scope<'a>,
+    label: String,
+    current: String,
+    ports: Vec<String>,
+    on_change: EventHandler<'a, String>,
+) -> Element<'a> {
+    let missing = !ports.contains(current);
+    cx.render(rsx!(
+        LabeledSelect { label: label.to_string(),
+            select {
+                class: "flex-1 rounded-md border-2 min-w-32 px-5 py-2 mx-2",
+                onchange: move |e| on_change.call(e.value.to_string()),
+                if missing {
+                    rsx!(option { selected: true, disabled: true, "{current} (missing)" })
+                }
+                for name in ports.iter() {
+                    option { selected: current == name, value: "{name}", "{name}" }
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn ChannelSelect<'a>(
+    cx: Scope<'a>,
+    label: String,
+    none_label: String,
+    #[props(!optional)] current: Option<MidiChannel>,
+    on_change: EventHandler<'a, Option<MidiChannel>>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        LabeledSelect { label: label.to_string(),
+            select {
+                class: "flex-1 rounded-md border-2 px-5 py-2 mx-2",
+                onchange: move |e| {
+                    on_change.call(e.value.parse().ok().and_then(|n| MidiChannel::from_number(n).ok()))
+                },
+                option { selected: current.is_none(), value: "", "{none_label}" }
+                for channel in MidiChannel::all() {
+                    option { selected: *current == Some(channel), value: "{channel}", "{channel}" }
+                }
+            }
+        }
+    ))
+}
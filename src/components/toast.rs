@@ -0,0 +1,86 @@
+use dioxus::prelude::*;
+use gloo_timers::future::TimeoutFuture;
+
+use crate::midi::scheduler::SendFailure;
+
+/// How long a notification stays up unless it is dismissed.
+const TOAST_DURATION_MS: u32 = 8000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    pub id: u64,
+    pub title: String,
+    pub message: String,
+}
+
+/// Notifications currently on screen, newest last.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Toasts {
+    next_id: u64,
+    pub toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    fn push(&mut self, title: String, message: String) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.toasts.push(Toast { id, title, message });
+        id
+    }
+
+    fn dismiss(&mut self, id: u64) {
+        self.toasts.retain(|toast| toast.id != id);
+    }
+}
+
+/// Provides `Toasts` to every component below.
+pub fn use_toast_provider(cx: &ScopeState) {
+    use_shared_state_provider(cx, Toasts::default);
+}
+
+pub fn use_toasts(cx: &ScopeState) -> &UseSharedState<Toasts> {
+    use_shared_state::<Toasts>(cx).unwrap()
+}
+
+/// Shows a notification for `TOAST_DURATION_MS`.
+pub fn show(toasts: &UseSharedState<Toasts>, title: String, message: String) {
+    let id = toasts.write().push(title, message);
+    let toasts = toasts.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        TimeoutFuture::new(TOAST_DURATION_MS).await;
+        toasts.write().dismiss(id);
+    });
+}
+
+/// Reports a step of `preset` that failed to send, numbering steps from 1 like the editor.
+pub fn show_failure(toasts: &UseSharedState<Toasts>, preset: &str, failure: &SendFailure) {
+    show(
+        toasts,
+        format!("{}: step {} failed", preset, failure.step + 1),
+        format!("{}: {}", failure.device, failure.error),
+    );
+}
+
+#[component]
+pub fn ToastArea(cx: Scope) -> Element {
+    let toasts = use_toasts(cx);
+    let shown = toasts.read().toasts.clone();
+    cx.render(rsx!(
+        div { class: "fixed bottom-4 right-4 z-50 flex flex-col gap-2 w-80",
+            for toast in shown.into_iter() {
+                div { class: "flex items-start rounded-md border-2 border-red-500 bg-red-50 p-3 shadow-lg text-sm",
+                    span { class: "fas fa-triangle-exclamation text-red-600 mr-2 mt-1" }
+                    div { class: "flex-1",
+                        div { class: "font-bold text-red-700", "{toast.title}" }
+                        div { class: "text-red-700", "{toast.message}" }
+                    }
+                    button {
+                        class: "ml-2 text-red-700 hover:text-red-900",
+                        onclick: move |_| toasts.write().dismiss(toast.id),
+                        span { class: "fas fa-xmark" }
+                    }
+                }
+            }
+        }
+    ))
+}
@@ -0,0 +1,332 @@
+use dioxus::prelude::*;
+use dioxus_router::prelude::use_navigator;
+
+use crate::components::data::{use_persistent, AppData, TriggerRule};
+use crate::components::devices::{open_inputs, use_inputs};
+use crate::components::monitor::use_refresh;
+use crate::components::preset::{Badge, Button};
+use crate::components::toast::{show_failure, use_toasts, Toasts};
+use crate::midi::channel::MidiChannel;
+use crate::midi::input;
+use crate::midi::midi_message::MidiMessage;
+use crate::midi::monitor::describe;
+use crate::midi::scheduler::play;
+use crate::midi::translator;
+use crate::midi::translator::{MessagePattern, PatternKind};
+use crate::midi::value::U7;
+use crate::Route;
+
+/// Plays presets in response to incoming messages for as long as the app is open, and keeps the
+/// inputs used by rules open as they come and go.
+pub fn use_translator(cx: &ScopeState) {
+    let inputs = use_inputs(cx);
+    let toasts = use_toasts(cx).clone();
+    cx.use_hook(move || {
+        set_rules(&AppData::stored().rules);
+        input::listen(move |port, message| trigger(&toasts, port, message))
+    });
+    use_effect(cx, (&inputs,), |(inputs,)| async move {
+        let data = AppData::stored();
+        open_inputs(data.rules.iter().map(|rule| &rule.pattern.input), &inputs);
+    });
+}
+
+/// Makes `rules` the ones incoming messages are checked against.
+fn set_rules(rules: &[TriggerRule]) {
+    translator::set_patterns(rules.iter().map(|rule| rule.pattern.clone()).collect());
+}
+
+/// Plays the preset of the first rule matching `message`. Storage is only read once a message
+/// matches a rule, and the rule is looked up again there, so presets deleted since the rules were
+/// set are not played.
+fn trigger(toasts: &UseSharedState<Toasts>, port: &str, message: &MidiMessage) {
+    if !translator::any_matches(port, message) {
+        return;
+    }
+    let data = AppData::stored();
+    let Some((i, rule)) = data
+        .rules
+        .iter()
+        .enumerate()
+        .find(|(_, rule)| rule.pattern.matches(port, message))
+    else {
+        return;
+    };
+    let Some(preset) = data.presets.get(rule.preset) else {
+        log::warn!("Rule {} refers to a preset that no longer exists", i);
+        return;
+    };
+    log::info!(
+        "Rule {} ({}) fired, playing {}",
+        i,
+        rule.pattern,
+        preset.label
+    );
+    translator::log_fired(i, &preset.label, message);
+    let toasts = toasts.clone();
+    let label = preset.label.clone();
+    play(
+        &preset.device,
+        preset.messages.clone(),
+        |device| data.device_settings(device).bank_select,
+        |_| (),
+        move |failure| show_failure(&toasts, &label, &failure),
+    );
+}
+
+#[component]
+pub fn TranslatorView(cx: Scope) -> Element {
+    let data = use_persistent(cx, "app_data", AppData::default);
+    let nav = use_navigator(cx);
+    let inputs = use_inputs(cx);
+    use_refresh(cx, translator::version);
+
+    let presets: Vec<String> = data
+        .get()
+        .presets
+        .iter()
+        .map(|preset| preset.label.clone())
+        .collect();
+    let fired = translator::fired();
+    let first_input = inputs.first().cloned().unwrap_or_default();
+
+    let save = move |rules: Vec<TriggerRule>| {
+        set_rules(&rules);
+        data.set(AppData {
+            rules,
+            ..data.get()
+        });
+    };
+
+    cx.render(rsx!(
+        div { class: "container bg-slate-50 mx-auto px-2 pt-2 text-sm mb-4",
+            div { class: "flex",
+                Button {
+                    styling: "",
+                    text: "Back to Dashboard",
+                    icon: "fas fa-rotate-back",
+                    on_click: move |_| {
+                        nav.push(Route::DashboardView {});
+                    }
+                }
+                Button {
+                    styling: "bg-teal-300 hover:bg-teal-500 border-teal-500",
+                    text: "Add Rule",
+                    icon: "fas fa-plus",
+                    on_click: move |_| {
+                        let mut rules = data.get().rules;
+                        rules
+                            .push(TriggerRule {
+                                pattern: MessagePattern {
+                                    input: first_input.clone(),
+                                    ..MessagePattern::default()
+                                },
+                                preset: 0,
+                            });
+                        save(rules);
+                    }
+                }
+                Button {
+                    styling: "",
+                    text: "Clear Log",
+                    icon: "fas fa-trash",
+                    on_click: move |_| translator::clear_log()
+                }
+            }
+            if presets.is_empty() {
+                rsx!(div { class: "text-gray-600 mt-4", "Add a preset before adding rules." })
+            }
+            for (i , rule) in data.get().rules.into_iter().enumerate() {
+                RuleView {
+                    index: i,
+                    rule: rule,
+                    inputs: inputs.clone(),
+                    presets: presets.clone(),
+                    on_change: move |rule: TriggerRule| {
+                        if let Err(err) = input::open(&rule.pattern.input) {
+                            log::error!("Error opening MIDI input {}: {}", rule.pattern.input, err);
+                        }
+                        let mut rules = data.get().rules;
+                        rules[i] = rule;
+                        save(rules);
+                    },
+                    on_delete: move |_| {
+                        let mut rules = data.get().rules;
+                        rules.remove(i);
+                        save(rules);
+                    }
+                }
+            }
+            h5 { class: "text-lg font-bold mt-6", "Fired Rules" }
+            table { class: "table-auto w-full mt-2 font-mono text-xs",
+                thead {
+                    tr { class: "text-left border-b-2",
+                        th { "Time (ms)" }
+                        th { "Rule" }
+                        th { "Message" }
+                        th { "Preset" }
+                    }
+                }
+                tbody {
+                    for entry in fired.into_iter().rev() {
+                        tr { class: "border-b",
+                            td { format!("{:.1}", entry.timestamp) }
+                            td { "{entry.rule}" }
+                            td { describe(&entry.message) }
+                            td { "{entry.preset}" }
+                        }
+                    }
+                }
+            }
+        }
+    ))
+}
+
+#[component]
+fn RuleView<'a>(
+    cx: Scope<'a>,
+    index: usize,
+    rule: TriggerRule,
+    inputs: Vec<String>,
+    presets: Vec<String>,
+    on_change: EventHandler<'a, TriggerRule>,
+    on_delete: EventHandler<'a>,
+) -> Element<'a> {
+    let pattern = &rule.pattern;
+    let missing = !inputs.contains(&pattern.input);
+    cx.render(rsx!(
+        div { class: "flex flex-wrap items-end py-1 border-b",
+            span { class: "border-2 rounded-md flex items-center p-2 font-mono bg-grey-300", "{index}" }
+            LabeledSelect { label: "Input".to_string(),
+                select {
+                    class: "flex-1 rounded-md border-2 min-w-32 px-5 py-2 mx-2",
+                    onchange: move |e| {
+                        on_change.call(TriggerRule {
+                            pattern: MessagePattern { input: e.value.to_string(), ..pattern.clone() },
+                            ..rule.clone()
+                        })
+                    },
+                    if missing {
+                        rsx!(option { selected: true, disabled: true, "{pattern.input} (missing)" })
+                    }
+                    for name in inputs.iter() {
+                        option { selected: &pattern.input == name, value: "{name}", "{name}" }
+                    }
+                }
+            }
+            LabeledSelect { label: "Type".to_string(),
+                select {
+                    class: "flex-1 rounded-md border-2 px-5 py-2 mx-2",
+                    onchange: move |e| {
+                        if let Some(kind) = enum_iterator::all::<PatternKind>().find(|kind| kind.to_string() == e.value) {
+                            on_change.call(TriggerRule {
+                                pattern: MessagePattern { kind, ..pattern.clone() },
+                                ..rule.clone()
+                            })
+                        }
+                    },
+                    for kind in enum_iterator::all::<PatternKind>() {
+                        option { selected: pattern.kind == kind, "{kind}" }
+                    }
+                }
+            }
+            LabeledSelect { label: "Channel".to_string(),
+                select {
+                    class: "flex-1 rounded-md border-2 px-5 py-2 mx-2",
+                    onchange: move |e| {
+                        let channel = e.value.parse().ok().and_then(|n| MidiChannel::from_number(n).ok());
+                        on_change.call(TriggerRule {
+                            pattern: MessagePattern { channel, ..pattern.clone() },
+                            ..rule.clone()
+                        })
+                    },
+                    option { selected: pattern.channel.is_none(), value: "", "Any" }
+                    for channel in MidiChannel::all() {
+                        option { selected: pattern.channel == Some(channel), value: "{channel}", "{channel}" }
+                    }
+                }
+            }
+            OptionalDataView {
+                label: match pattern.kind {
+                    PatternKind::ProgramChange => "Program".to_string(),
+                    PatternKind::ControlChange => "CC Number".to_string(),
+                    PatternKind::NoteOn => "Note".to_string(),
+                },
+                current_value: pattern.number,
+                on_change: move |number: Option<U7>| {
+                    on_change.call(TriggerRule {
+                        pattern: MessagePattern { number, ..pattern.clone() },
+                        ..rule.clone()
+                    })
+                }
+            }
+            if pattern.kind != PatternKind::ProgramChange {
+                rsx!(OptionalDataView {
+                    label: match pattern.kind {
+                        PatternKind::NoteOn => "Velocity".to_string(),
+                        _ => "CC Value".to_string(),
+                    },
+                    current_value: pattern.value,
+                    on_change: move |value: Option<U7>| {
+                        on_change.call(TriggerRule {
+                            pattern: MessagePattern { value, ..pattern.clone() },
+                            ..rule.clone()
+                        })
+                    }
+                })
+            }
+            span { class: "flex items-center px-2 py-2", span { class: "fas fa-arrow-right" } }
+            LabeledSelect { label: "Preset".to_string(),
+                select {
+                    class: "flex-1 rounded-md border-2 min-w-32 px-5 py-2 mx-2",
+                    onchange: move |e| {
+                        if let Ok(preset) = e.value.parse() {
+                            on_change.call(TriggerRule { preset, ..rule.clone() })
+                        }
+                    },
+                    for (i , label) in presets.iter().enumerate() {
+                        option { selected: rule.preset == i, value: "{i}", "{label}" }
+                    }
+                }
+            }
+            Button {
+                styling: "bg-red-300 hover:bg-red-500 border-red-500",
+                text: "Delete",
+                icon: "fas fa-trash",
+                on_click: move |_| on_delete.call(())
+            }
+        }
+    ))
+}
+
+#[component]
+pub fn LabeledSelect<'a>(cx: Scope<'a>, label: String, children: Element<'a>) -> Element<'a> {
+    cx.render(rsx!(
+        div { class: "flex flex-col",
+            span { class: "pl-2", Badge { text: label.to_string() } }
+            children
+        }
+    ))
+}
+
+/// A 7-bit value that can also be left open to match anything.
+#[component]
+fn OptionalDataView<'a>(
+    cx: Scope<'a>,
+    label: String,
+    #[props(!optional)] current_value: Option<U7>,
+    on_change: EventHandler<'a, Option<U7>>,
+) -> Element<'a> {
+    cx.render(rsx!(
+        LabeledSelect { label: label.to_string(),
+            select {
+                class: "flex-1 rounded-md border-2 px-5 py-2 mx-2",
+                onchange: move |e| on_change.call(e.value.parse::<u8>().ok().and_then(|n| U7::new(n).ok())),
+                option { selected: current_value.is_none(), value: "", "Any" }
+                for n in 0..=U7::MAX {
+                    option { selected: current_value.map(U7::get) == Some(n), value: "{n}", "{n}" }
+                }
+            }
+        }
+    ))
+}
@@ -4,8 +4,18 @@ use dioxus::prelude::*;
 use dioxus_router::prelude::{Routable, Router};
 use log::LevelFilter;
 
-use crate::components::{dashboard::DashboardView, preset::PresetView};
-use crate::midi::midi_message::{send_midi_messages, MidiMessage};
+use crate::components::devices::use_device_watcher;
+use crate::components::permission::use_permission_provider;
+use crate::components::routing::use_routing;
+use crate::components::toast::{show_failure, use_toast_provider, use_toasts, ToastArea};
+use crate::components::translator::use_translator;
+use crate::components::{
+    dashboard::DashboardView, monitor::MonitorView, preset::PresetView, routing::RoutingView,
+    translator::TranslatorView,
+};
+use crate::midi::bank_select::BankSelectMode;
+use crate::midi::device::DeviceRef;
+use crate::midi::midi_message::{send_midi_messages, MidiMessage, PresetMessage};
 
 mod components;
 mod midi;
@@ -18,6 +28,15 @@ pub enum Route {
 
     #[route("/preset/:id")]
     PresetView { id: usize },
+
+    #[route("/monitor")]
+    MonitorView {},
+
+    #[route("/translator")]
+    TranslatorView {},
+
+    #[route("/routing")]
+    RoutingView {},
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -30,19 +49,34 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn App(cx: Scope) -> Element {
+    use_device_watcher(cx);
+    use_toast_provider(cx);
+    use_permission_provider(cx);
+    use_translator(cx);
+    use_routing(cx);
     cx.render(rsx!(
         div { class: "container mx-auto h-screen", Router::<Route> {} }
+        ToastArea {}
     ))
 }
 
 #[component]
 fn SendMessages<'a>(cx: Scope<'a>, midi_messages: &'a UseRef<Vec<MidiMessage>>) -> Element {
+    let toasts = use_toasts(cx);
     cx.render(rsx!(
         button {
             class: "btn btn-warning",
             onclick: move |_| {
                 log::info!("Sending messages: {:?}", midi_messages.read().clone());
-                send_midi_messages(1, midi_messages.read().clone())
+                send_midi_messages(
+                    &DeviceRef::Index(1),
+                    midi_messages.read().iter().cloned().map(PresetMessage::from).collect(),
+                    |_| BankSelectMode::default(),
+                    {
+                        let toasts = toasts.clone();
+                        move |failure| show_failure(&toasts, "Send messages", &failure)
+                    },
+                )
             },
             "Send messages"
         }
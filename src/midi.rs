@@ -1,7 +1,31 @@
+pub mod bank_select;
+pub mod ble;
 pub mod channel;
+pub mod clock;
+pub mod connection;
 pub mod control_change;
+pub mod device;
+pub mod error;
 pub mod fractal;
+pub mod input;
 pub mod midi_message;
+pub mod monitor;
+pub mod note;
+pub mod parameter_number;
+pub mod parser;
+pub mod permission;
+pub mod pitch_bend;
+pub mod pressure;
 pub mod program_change;
+pub mod routing;
+pub mod scheduler;
+pub mod smf;
+pub mod sysex;
+pub mod translator;
+pub mod transport;
+pub mod value;
+pub mod watcher;
+#[cfg(target_arch = "wasm32")]
+pub mod web_midi;
 
 pub mod midi_block;
@@ -0,0 +1,119 @@
+use enum_display::EnumDisplay;
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use crate::midi::channel::MidiChannel;
+use crate::midi::value::U14;
+use std::fmt::{Display, Formatter};
+
+const BANK_SELECT_MSB: u8 = 0;
+const BANK_SELECT_LSB: u8 = 32;
+const PROGRAMS_PER_BANK: u16 = 128;
+
+/// How a device expects the bank number: on CC 0, on CC 32, or split across both.
+#[derive(Debug, Clone, PartialEq, Sequence, EnumDisplay, Copy, Serialize, Deserialize, Default)]
+pub enum BankSelectMode {
+    #[default]
+    Msb,
+    Lsb,
+    Both,
+}
+
+/// A Program Change addressed by absolute preset number, preceded by the bank select needed to
+/// reach it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct BankProgramChange {
+    pub channel: MidiChannel,
+    pub preset: U14,
+}
+
+impl Display for BankProgramChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bank PC: {:?}", self)
+    }
+}
+
+impl BankProgramChange {
+    pub fn bank(&self) -> u16 {
+        self.preset.get() / PROGRAMS_PER_BANK
+    }
+
+    pub fn program(&self) -> u8 {
+        (self.preset.get() % PROGRAMS_PER_BANK) as u8
+    }
+
+    /// The same bank with another program in it.
+    pub fn with_program(&self, program: u8) -> BankProgramChange {
+        let preset = self.bank() * PROGRAMS_PER_BANK + (program as u16 % PROGRAMS_PER_BANK);
+        BankProgramChange {
+            channel: self.channel,
+            preset: U14::new(preset).unwrap_or(self.preset),
+        }
+    }
+
+    pub fn as_u8_with(&self, mode: BankSelectMode) -> Vec<u8> {
+        let status = self.channel.status(0xB0);
+        let bank = self.bank();
+        let mut bytes = match mode {
+            BankSelectMode::Msb => vec![status, BANK_SELECT_MSB, bank as u8],
+            BankSelectMode::Lsb => vec![status, BANK_SELECT_LSB, bank as u8],
+            BankSelectMode::Both => vec![
+                status,
+                BANK_SELECT_MSB,
+                (bank >> 7) as u8,
+                status,
+                BANK_SELECT_LSB,
+                (bank & 0x7F) as u8,
+            ],
+        };
+        bytes.extend([self.channel.status(0xC0), self.program()]);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(channel: i32, preset: u16) -> BankProgramChange {
+        BankProgramChange {
+            channel: MidiChannel::from_number(channel).unwrap(),
+            preset: U14::new(preset).unwrap(),
+        }
+    }
+
+    #[test]
+    fn splits_the_preset_into_bank_and_program() {
+        let change = change(1, 2 * 128 + 44);
+        assert_eq!((change.bank(), change.program()), (2, 44));
+        assert_eq!(change.with_program(130).preset.get(), 2 * 128 + 2);
+    }
+
+    #[test]
+    fn sends_the_bank_on_msb() {
+        assert_eq!(
+            change(3, 2 * 128 + 44).as_u8_with(BankSelectMode::Msb),
+            vec![0xB2, 0x00, 0x02, 0xC2, 0x2C]
+        );
+    }
+
+    #[test]
+    fn sends_the_bank_on_lsb() {
+        assert_eq!(
+            change(3, 2 * 128 + 44).as_u8_with(BankSelectMode::Lsb),
+            vec![0xB2, 0x20, 0x02, 0xC2, 0x2C]
+        );
+    }
+
+    #[test]
+    fn sends_the_bank_on_both() {
+        assert_eq!(
+            change(16, 2 * 128 + 44).as_u8_with(BankSelectMode::Both),
+            vec![0xBF, 0x00, 0x00, 0xBF, 0x20, 0x02, 0xCF, 0x2C]
+        );
+        assert_eq!(
+            change(1, U14::MAX).as_u8_with(BankSelectMode::Both),
+            vec![0xB0, 0x00, 0x00, 0xB0, 0x20, 0x7F, 0xC0, 0x7F]
+        );
+    }
+}
@@ -0,0 +1,576 @@
+//! BLE-MIDI packet encoding and decoding, for devices reached through Web Bluetooth.
+//!
+//! Each packet starts with a header byte carrying the high six bits of a 13-bit millisecond
+//! timestamp, and every message in it is preceded by a timestamp byte carrying the low seven
+//! bits. Within a packet, channel messages may use running status, and a message sent at the
+//! same time as the one before it may leave out its timestamp byte. SysEx longer than a packet
+//! continues in the next one right after the header, and its closing `F7` gets its own
+//! timestamp byte. For example, a note on followed by a note off 1 ms later, at 200 ms:
+//!
+//! ```text
+//! 81 C8 90 3C 7F C9 80 3C 00
+//! ```
+//!
+//! Packets are at most the negotiated ATT MTU less three bytes, which is 20 bytes unless the
+//! device negotiates more.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::midi::clock::TIMING_CLOCK;
+use crate::midi::parser::data_length;
+use crate::midi::program_change::AsU8;
+use crate::midi::sysex::{SYSEX_END, SYSEX_START};
+
+// Nothing connects over Web Bluetooth yet, so outside of tests the entry points below are unused.
+
+/// The BLE-MIDI GATT service.
+#[cfg_attr(not(test), allow(dead_code))]
+pub const SERVICE_UUID: &str = "03b80e5a-ede8-4b33-a751-6ce34ec4c700";
+/// The characteristic packets are written to and notified on.
+#[cfg_attr(not(test), allow(dead_code))]
+pub const CHARACTERISTIC_UUID: &str = "7772e5db-3868-4112-a1a9-f2669d106bf3";
+
+/// Packet size with the default ATT MTU of 23 bytes.
+#[cfg_attr(not(test), allow(dead_code))]
+pub const DEFAULT_MAX_PACKET: usize = 20;
+/// Smallest packet any message fits in: header, timestamp and a three-byte message.
+pub const MIN_PACKET: usize = 5;
+/// Timestamps wrap around after this many milliseconds.
+pub const TIMESTAMP_RANGE: u16 = 1 << 13;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BleError {
+    EmptyPacket,
+    InvalidHeader(u8),
+    UnexpectedByte { offset: usize, byte: u8 },
+    UnexpectedEnd,
+}
+
+impl Display for BleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BleError::EmptyPacket => write!(f, "BLE-MIDI packet is empty"),
+            BleError::InvalidHeader(byte) => {
+                write!(f, "Invalid BLE-MIDI packet header {:02X}", byte)
+            }
+            BleError::UnexpectedByte { offset, byte } => {
+                write!(f, "Unexpected byte {:02X} at offset {}", byte, offset)
+            }
+            BleError::UnexpectedEnd => write!(f, "BLE-MIDI packet ends inside a message"),
+        }
+    }
+}
+
+impl Error for BleError {}
+
+/// A single MIDI message and its timestamp, in milliseconds modulo `TIMESTAMP_RANGE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BleMessage {
+    pub timestamp: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// Packs messages into as few packets as possible.
+#[derive(Debug)]
+pub struct BleEncoder {
+    max_packet: usize,
+    packets: Vec<Vec<u8>>,
+    packet: Vec<u8>,
+    running_status: Option<u8>,
+    last_timestamp: Option<u16>,
+}
+
+impl BleEncoder {
+    /// `max_packet` is the negotiated ATT MTU less three; anything below `MIN_PACKET` is raised
+    /// to it.
+    pub fn new(max_packet: usize) -> BleEncoder {
+        BleEncoder {
+            max_packet: max_packet.max(MIN_PACKET),
+            packets: vec![],
+            packet: vec![],
+            running_status: None,
+            last_timestamp: None,
+        }
+    }
+
+    /// Adds `message` to go out at `timestamp`, in milliseconds. Messages that encode to several
+    /// wire messages, such as NRPN, are split and share the timestamp.
+    pub fn push(&mut self, timestamp: u64, message: &impl AsU8) {
+        self.push_bytes(timestamp, &message.as_u8());
+    }
+
+    /// Like `push`, for raw wire bytes.
+    pub fn push_bytes(&mut self, timestamp: u64, bytes: &[u8]) {
+        let timestamp = (timestamp % TIMESTAMP_RANGE as u64) as u16;
+        for message in split(bytes) {
+            match message[0] {
+                SYSEX_START => self.push_sysex(timestamp, message),
+                _ => self.push_message(timestamp, message),
+            }
+        }
+    }
+
+    /// The packets for everything pushed, in the order they are to be written.
+    pub fn finish(mut self) -> Vec<Vec<u8>> {
+        self.flush();
+        self.packets
+    }
+
+    fn push_message(&mut self, timestamp: u16, message: &[u8]) {
+        let status = message[0];
+        let running = status < 0xF0 && self.running_status == Some(status);
+        let mut encoded = vec![];
+        if !running || self.last_timestamp != Some(timestamp) {
+            encoded.push(timestamp_byte(timestamp));
+        }
+        encoded.extend(if running { &message[1..] } else { message });
+        if !self.fits(timestamp, encoded.len()) {
+            // A fresh packet always has room, and starts without running status.
+            self.flush();
+            return self.push_message(timestamp, message);
+        }
+        self.start(timestamp);
+        self.packet.extend(encoded);
+        self.last_timestamp = Some(timestamp);
+        match status {
+            0x80..=0xEF => self.running_status = Some(status),
+            // Realtime messages leave running status alone.
+            TIMING_CLOCK.. => (),
+            _ => self.running_status = None,
+        }
+    }
+
+    /// Writes SysEx across as many packets as it takes.
+    fn push_sysex(&mut self, timestamp: u16, message: &[u8]) {
+        let (body, end) = match message.split_last() {
+            Some((&SYSEX_END, body)) => (body, true),
+            _ => (message, false),
+        };
+        if !self.fits(timestamp, 2) {
+            self.flush();
+        }
+        self.start(timestamp);
+        self.packet.push(timestamp_byte(timestamp));
+        for byte in body {
+            if self.packet.len() >= self.max_packet {
+                self.flush();
+                self.start(timestamp);
+            }
+            self.packet.push(*byte);
+        }
+        if end {
+            if self.packet.len() + 2 > self.max_packet {
+                self.flush();
+                self.start(timestamp);
+            }
+            self.packet.extend([timestamp_byte(timestamp), SYSEX_END]);
+        }
+        self.running_status = None;
+        self.last_timestamp = Some(timestamp);
+    }
+
+    /// Whether `length` more bytes at `timestamp` fit in the current packet. Timestamps in a
+    /// packet never go back, and share the high bits in its header.
+    fn fits(&self, timestamp: u16, length: usize) -> bool {
+        match self.last_timestamp {
+            None => length < self.max_packet,
+            Some(last) => {
+                timestamp >= last
+                    && timestamp >> 7 == last >> 7
+                    && self.packet.len() + length <= self.max_packet
+            }
+        }
+    }
+
+    fn start(&mut self, timestamp: u16) {
+        if self.packet.is_empty() {
+            self.packet.push(0x80 | (timestamp >> 7) as u8);
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.packet.is_empty() {
+            self.packets.push(std::mem::take(&mut self.packet));
+        }
+        self.running_status = None;
+        self.last_timestamp = None;
+    }
+}
+
+/// Encodes `messages`, given with their timestamps in milliseconds, into packets of at most
+/// `max_packet` bytes.
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn encode<'a, M: AsU8 + 'a>(
+    messages: impl IntoIterator<Item = (u64, &'a M)>,
+    max_packet: usize,
+) -> Vec<Vec<u8>> {
+    let mut encoder = BleEncoder::new(max_packet);
+    for (timestamp, message) in messages {
+        encoder.push(timestamp, message);
+    }
+    encoder.finish()
+}
+
+/// Turns packets back into messages. Running status and unfinished SysEx carry over from one
+/// packet to the next, so packets must be decoded in the order they arrived.
+#[derive(Debug, Default)]
+pub struct BleDecoder {
+    running_status: Option<u8>,
+    sysex: Option<BleMessage>,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl BleDecoder {
+    pub fn new() -> BleDecoder {
+        BleDecoder::default()
+    }
+
+    pub fn decode(&mut self, packet: &[u8]) -> Result<Vec<BleMessage>, BleError> {
+        let (&header, _) = packet.split_first().ok_or(BleError::EmptyPacket)?;
+        if header & 0xC0 != 0x80 {
+            return Err(BleError::InvalidHeader(header));
+        }
+        let mut high = (header & 0x3F) as u16;
+        let mut timestamp: Option<u16> = None;
+        let mut messages = vec![];
+        let mut bytes = packet.iter().copied().enumerate().skip(1);
+        while let Some((offset, byte)) = bytes.next() {
+            if byte < 0x80 {
+                // Data straight after the header or a message: SysEx carrying on, or running
+                // status at the time of the message before.
+                if let Some(sysex) = self.sysex.as_mut() {
+                    sysex.bytes.push(byte);
+                    continue;
+                }
+                let (Some(timestamp), Some(status)) = (timestamp, self.running_status) else {
+                    return Err(BleError::UnexpectedByte { offset, byte });
+                };
+                messages.push(self.read(timestamp, status, Some(byte), &mut bytes)?);
+                continue;
+            }
+            // A timestamp byte, whose low bits going back means the high bits moved on.
+            let low = (byte & 0x7F) as u16;
+            if timestamp.is_some_and(|previous| low < previous & 0x7F) {
+                high = (high + 1) & 0x3F;
+            }
+            let now = high << 7 | low;
+            timestamp = Some(now);
+            let (offset, byte) = bytes.next().ok_or(BleError::UnexpectedEnd)?;
+            match byte {
+                TIMING_CLOCK.. => messages.push(BleMessage {
+                    timestamp: now,
+                    bytes: vec![byte],
+                }),
+                SYSEX_START => {
+                    self.running_status = None;
+                    self.sysex = Some(BleMessage {
+                        timestamp: now,
+                        bytes: vec![SYSEX_START],
+                    });
+                }
+                SYSEX_END => {
+                    let mut sysex = self
+                        .sysex
+                        .take()
+                        .ok_or(BleError::UnexpectedByte { offset, byte })?;
+                    sysex.bytes.push(SYSEX_END);
+                    messages.push(sysex);
+                }
+                0x80..=0xF6 => {
+                    self.sysex = None;
+                    messages.push(self.read(now, byte, None, &mut bytes)?);
+                }
+                _ => {
+                    let Some(status) = self.running_status else {
+                        return Err(BleError::UnexpectedByte { offset, byte });
+                    };
+                    messages.push(self.read(now, status, Some(byte), &mut bytes)?);
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Reads the rest of a message with `status`, whose first data byte may already be read.
+    fn read(
+        &mut self,
+        timestamp: u16,
+        status: u8,
+        first: Option<u8>,
+        bytes: &mut impl Iterator<Item = (usize, u8)>,
+    ) -> Result<BleMessage, BleError> {
+        let mut message = vec![status];
+        message.extend(first);
+        while message.len() < 1 + data_length(status) {
+            match bytes.next() {
+                Some((_, byte)) if byte < 0x80 => message.push(byte),
+                Some((offset, byte)) => return Err(BleError::UnexpectedByte { offset, byte }),
+                None => return Err(BleError::UnexpectedEnd),
+            }
+        }
+        self.running_status = Some(status).filter(|status| *status < 0xF0);
+        Ok(BleMessage {
+            timestamp,
+            bytes: message,
+        })
+    }
+}
+
+fn timestamp_byte(timestamp: u16) -> u8 {
+    0x80 | (timestamp & 0x7F) as u8
+}
+
+/// Splits wire bytes into single messages. Data bytes without a status in front are dropped.
+fn split(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut messages = vec![];
+    let mut rest = bytes;
+    while let Some(&status) = rest.first() {
+        let length = match status {
+            0x00..=0x7F => {
+                rest = &rest[1..];
+                continue;
+            }
+            SYSEX_START => rest
+                .iter()
+                .position(|byte| *byte == SYSEX_END)
+                .map_or(rest.len(), |end| end + 1),
+            TIMING_CLOCK.. => 1,
+            _ => 1 + data_length(status),
+        };
+        let (message, tail) = rest.split_at(length.min(rest.len()));
+        messages.push(message);
+        rest = tail;
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::channel::MidiChannel;
+    use crate::midi::note::{NoteOff, NoteOn};
+    use crate::midi::parameter_number::Nrpn;
+    use crate::midi::sysex::SysEx;
+    use crate::midi::value::{U14, U7};
+
+    fn hex(packet: &str) -> Vec<u8> {
+        packet
+            .split_whitespace()
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect()
+    }
+
+    fn message(timestamp: u16, bytes: &[u8]) -> BleMessage {
+        BleMessage {
+            timestamp,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    fn note_on(note: u8) -> NoteOn {
+        NoteOn {
+            channel: MidiChannel::default(),
+            note: U7::new(note).unwrap(),
+            velocity: U7::new(0x7F).unwrap(),
+        }
+    }
+
+    #[test]
+    fn encodes_and_decodes_the_example_packet() {
+        let note_off = NoteOff {
+            channel: MidiChannel::default(),
+            note: U7::new(0x3C).unwrap(),
+            velocity: U7::default(),
+        };
+        let mut encoder = BleEncoder::new(DEFAULT_MAX_PACKET);
+        encoder.push(200, &note_on(0x3C));
+        encoder.push(201, &note_off);
+        let packet = hex("81 C8 90 3C 7F C9 80 3C 00");
+        assert_eq!(encoder.finish(), vec![packet.clone()]);
+        assert_eq!(
+            BleDecoder::new().decode(&packet),
+            Ok(vec![
+                message(200, &[0x90, 0x3C, 0x7F]),
+                message(201, &[0x80, 0x3C, 0x00]),
+            ])
+        );
+    }
+
+    #[test]
+    fn running_status_may_leave_out_the_timestamp() {
+        let same_time = hex("80 80 90 3C 7F 3E 7F");
+        let later = hex("80 80 90 3C 7F 81 3E 7F");
+        let notes = [note_on(0x3C), note_on(0x3E)];
+        assert_eq!(
+            encode([(0, &notes[0]), (0, &notes[1])], DEFAULT_MAX_PACKET),
+            vec![same_time.clone()]
+        );
+        assert_eq!(
+            encode([(0, &notes[0]), (1, &notes[1])], DEFAULT_MAX_PACKET),
+            vec![later.clone()]
+        );
+        assert_eq!(
+            BleDecoder::new().decode(&same_time),
+            Ok(vec![
+                message(0, &[0x90, 0x3C, 0x7F]),
+                message(0, &[0x90, 0x3E, 0x7F]),
+            ])
+        );
+        assert_eq!(
+            BleDecoder::new().decode(&later),
+            Ok(vec![
+                message(0, &[0x90, 0x3C, 0x7F]),
+                message(1, &[0x90, 0x3E, 0x7F]),
+            ])
+        );
+    }
+
+    #[test]
+    fn composite_messages_share_running_status() {
+        let nrpn = Nrpn {
+            channel: MidiChannel::default(),
+            parameter: U14::new(0x0102).unwrap(),
+            value: U14::new(0x0304).unwrap(),
+        };
+        assert_eq!(
+            encode([(0, &nrpn)], DEFAULT_MAX_PACKET),
+            vec![hex("80 80 B0 63 02 62 02 06 06 26 04")]
+        );
+    }
+
+    #[test]
+    fn splits_sysex_across_packets() {
+        let data: Vec<u8> = (0..30).collect();
+        let mut bytes = vec![SYSEX_START];
+        bytes.extend(&data);
+        bytes.push(SYSEX_END);
+        let sysex = SysEx::new(bytes.clone()).unwrap();
+
+        let packets = encode([(0, &sysex)], DEFAULT_MAX_PACKET);
+        let mut first = hex("80 80 F0");
+        first.extend(&data[..17]);
+        let mut second = hex("80");
+        second.extend(&data[17..]);
+        second.extend(hex("80 F7"));
+        assert_eq!(packets, vec![first, second]);
+
+        let mut decoder = BleDecoder::new();
+        assert_eq!(decoder.decode(&packets[0]), Ok(vec![]));
+        assert_eq!(decoder.decode(&packets[1]), Ok(vec![message(0, &bytes)]));
+    }
+
+    #[test]
+    fn packets_stay_within_the_limit() {
+        let notes: Vec<NoteOn> = (0..40).map(note_on).collect();
+        for max_packet in [MIN_PACKET, DEFAULT_MAX_PACKET, 64] {
+            let packets = encode(
+                notes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, note)| (i as u64 * 3, note)),
+                max_packet,
+            );
+            assert!(packets.iter().all(|packet| packet.len() <= max_packet));
+            let mut decoder = BleDecoder::new();
+            let decoded: Vec<BleMessage> = packets
+                .iter()
+                .flat_map(|packet| decoder.decode(packet).unwrap())
+                .collect();
+            let expected: Vec<BleMessage> = notes
+                .iter()
+                .enumerate()
+                .map(|(i, note)| message(i as u16 * 3, &note.as_u8()))
+                .collect();
+            assert_eq!(decoded, expected, "{}", max_packet);
+        }
+    }
+
+    #[test]
+    fn timestamps_wrap_within_a_packet() {
+        // The low bits going back means the high bits in the header moved on by one.
+        assert_eq!(
+            BleDecoder::new().decode(&hex("80 FF 90 3C 7F 81 80 3C 00")),
+            Ok(vec![
+                message(127, &[0x90, 0x3C, 0x7F]),
+                message(129, &[0x80, 0x3C, 0x00]),
+            ])
+        );
+        // And the 13-bit timestamp itself wraps to zero.
+        assert_eq!(
+            BleDecoder::new().decode(&hex("BF FF 90 3C 7F 80 80 3C 00")),
+            Ok(vec![
+                message(TIMESTAMP_RANGE - 1, &[0x90, 0x3C, 0x7F]),
+                message(0, &[0x80, 0x3C, 0x00]),
+            ])
+        );
+        let mut encoder = BleEncoder::new(DEFAULT_MAX_PACKET);
+        encoder.push(TIMESTAMP_RANGE as u64 + 5, &note_on(0x3C));
+        assert_eq!(encoder.finish(), vec![hex("80 85 90 3C 7F")]);
+    }
+
+    #[test]
+    fn realtime_bytes_may_interrupt_sysex() {
+        assert_eq!(
+            BleDecoder::new().decode(&hex("80 80 F0 01 02 81 F8 03 82 F7")),
+            Ok(vec![
+                message(1, &[TIMING_CLOCK]),
+                message(0, &[SYSEX_START, 0x01, 0x02, 0x03, SYSEX_END]),
+            ])
+        );
+    }
+
+    #[test]
+    fn undefined_statuses_have_no_data() {
+        assert_eq!(
+            BleDecoder::new().decode(&hex("80 80 F4 81 90 3C 7F")),
+            Ok(vec![message(0, &[0xF4]), message(1, &[0x90, 0x3C, 0x7F])])
+        );
+        assert_eq!(
+            split(&hex("F5 90 3C 7F")),
+            vec![&[0xF5][..], &[0x90, 0x3C, 0x7F][..]]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_packets() {
+        let mut decoder = BleDecoder::new();
+        assert_eq!(decoder.decode(&[]), Err(BleError::EmptyPacket));
+        assert_eq!(
+            decoder.decode(&hex("40 80 F8")),
+            Err(BleError::InvalidHeader(0x40))
+        );
+        assert_eq!(
+            decoder.decode(&hex("C0 80 F8")),
+            Err(BleError::InvalidHeader(0xC0))
+        );
+        assert_eq!(
+            decoder.decode(&hex("80 3C")),
+            Err(BleError::UnexpectedByte {
+                offset: 1,
+                byte: 0x3C
+            })
+        );
+        assert_eq!(
+            decoder.decode(&hex("80 80 90 3C")),
+            Err(BleError::UnexpectedEnd)
+        );
+        assert_eq!(
+            decoder.decode(&hex("80 80 90 3C 90")),
+            Err(BleError::UnexpectedByte {
+                offset: 4,
+                byte: 0x90
+            })
+        );
+    }
+
+    #[test]
+    fn describes_the_gatt_profile() {
+        assert_eq!(SERVICE_UUID.len(), 36);
+        assert_eq!(CHARACTERISTIC_UUID.len(), 36);
+        assert_eq!(
+            BleError::InvalidHeader(0x40).to_string(),
+            "Invalid BLE-MIDI packet header 40"
+        );
+    }
+}
@@ -1,52 +1,69 @@
-use enum_display::EnumDisplay;
-use enum_iterator::Sequence;
-
-#[derive(Debug, Clone, PartialEq, Sequence, EnumDisplay, Copy)]
-pub enum MidiChannel {
-    Ch1 = 0,
-    Ch2 = 1,
-    Ch3 = 2,
-    Ch4 = 3,
-    Ch5 = 4,
-    Ch6 = 5,
-    Ch7 = 6,
-    Ch8 = 7,
-    Ch9 = 8,
-    Ch10 = 9,
-    Ch11 = 10,
-    Ch12 = 11,
-    Ch13 = 12,
-    Ch14 = 13,
-    Ch15 = 14,
-    Ch16 = 15,
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+
+use crate::midi::value::MidiValueError;
+
+/// A MIDI channel.
+///
+/// Internally this is the 0–15 wire value that goes into the low nibble of a status byte. The
+/// UI, logs and stored presets use the 1–16 numbering; `from_number` and `number` are the only
+/// place where the two are converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct MidiChannel(u8);
+
+impl MidiChannel {
+    pub const FIRST: u8 = 1;
+    pub const LAST: u8 = 16;
+
+    /// Creates a channel from its 1–16 number.
+    pub fn from_number(number: i32) -> Result<MidiChannel, MidiValueError> {
+        match u8::try_from(number) {
+            Ok(n) if (MidiChannel::FIRST..=MidiChannel::LAST).contains(&n) => {
+                Ok(MidiChannel(n - MidiChannel::FIRST))
+            }
+            _ => Err(MidiValueError::ChannelOutOfRange(number)),
+        }
+    }
+
+    /// Takes the channel from the low nibble of a channel message status byte.
+    pub fn from_status(status: u8) -> MidiChannel {
+        MidiChannel(status & 0x0F)
+    }
+
+    /// All 16 channels in order.
+    pub fn all() -> impl Iterator<Item = MidiChannel> {
+        (0..MidiChannel::LAST).map(MidiChannel)
+    }
+
+    /// The 1–16 channel number.
+    pub fn number(self) -> u8 {
+        self.0 + MidiChannel::FIRST
+    }
+
+    /// Combines the channel with the high nibble of a channel message status, e.g. `0xB0`.
+    pub fn status(self, message: u8) -> u8 {
+        (message & 0xF0) | self.0
+    }
 }
 
-impl From<MidiChannel> for u8 {
-    fn from(cc: MidiChannel) -> u8 {
-        cc as u8
+impl Display for MidiChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.number())
     }
 }
 
-impl From<String> for MidiChannel {
-    fn from(s: String) -> Self {
-        match s.as_str() {
-            "Ch1" => MidiChannel::Ch1,
-            "Ch2" => MidiChannel::Ch2,
-            "Ch3" => MidiChannel::Ch6,
-            "Ch4" => MidiChannel::Ch4,
-            "Ch5" => MidiChannel::Ch5,
-            "Ch6" => MidiChannel::Ch6,
-            "Ch7" => MidiChannel::Ch7,
-            "Ch8" => MidiChannel::Ch8,
-            "Ch9" => MidiChannel::Ch9,
-            "Ch10" => MidiChannel::Ch10,
-            "Ch11" => MidiChannel::Ch11,
-            "Ch12" => MidiChannel::Ch12,
-            "Ch13" => MidiChannel::Ch13,
-            "Ch14" => MidiChannel::Ch14,
-            "Ch15" => MidiChannel::Ch15,
-            "Ch16" => MidiChannel::Ch16,
-            _ => panic!("Invalid MidiChannel"),
-        }
+impl Serialize for MidiChannel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.number())
+    }
+}
+
+/// Presets have always stored channels as 1–16, so they load unchanged. Values outside that range
+/// are clamped, like `U7`.
+impl<'de> Deserialize<'de> for MidiChannel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let number = u64::deserialize(deserializer)?;
+        let number = number.clamp(MidiChannel::FIRST as u64, MidiChannel::LAST as u64) as u8;
+        Ok(MidiChannel(number - MidiChannel::FIRST))
     }
 }
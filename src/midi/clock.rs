@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+
+use gloo_timers::callback::Interval;
+use serde::{Deserialize, Serialize};
+
+use crate::midi::connection;
+use crate::midi::error::MidiError;
+use crate::midi::program_change::AsU8;
+use crate::midi::value::U14;
+
+pub const PPQN: u32 = 24;
+pub const TIMING_CLOCK: u8 = 0xF8;
+pub const START: u8 = 0xFA;
+pub const CONTINUE: u8 = 0xFB;
+pub const STOP: u8 = 0xFC;
+pub const SONG_POSITION: u8 = 0xF2;
+
+/// How often the generator checks for due ticks; browsers clamp this to about 4 ms.
+const POLL_INTERVAL_MS: u32 = 2;
+
+pub const DEFAULT_BPM: u16 = 120;
+pub const MIN_BPM: u16 = 20;
+pub const MAX_BPM: u16 = 300;
+
+/// Song Position Pointer, counted in MIDI beats (sixteenth notes) since the start of the song.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct SongPosition {
+    pub position: U14,
+}
+
+impl Display for SongPosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Song Position: {:?}", self)
+    }
+}
+
+impl AsU8 for SongPosition {
+    fn as_u8(&self) -> Vec<u8> {
+        vec![SONG_POSITION, self.position.lsb(), self.position.msb()]
+    }
+}
+
+struct ClockState {
+    bpm: u16,
+    port: Option<String>,
+    started_at: f64,
+    ticks_sent: u64,
+    interval: Option<Interval>,
+}
+
+thread_local! {
+    static CLOCK: RefCell<ClockState> = const {
+        RefCell::new(ClockState {
+            bpm: DEFAULT_BPM,
+            port: None,
+            started_at: 0.0,
+            ticks_sent: 0,
+            interval: None,
+        })
+    };
+}
+
+/// Milliseconds since page load, with sub-millisecond resolution where the browser allows it.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+    web_sys::window()
+        .and_then(|w| w.performance())
+        .map(|p| p.now())
+        .unwrap_or_default()
+}
+
+/// Milliseconds since the clock was first read. Outside the browser there is no page, so this
+/// keeps timestamps from the loopback transport meaningful.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+    thread_local! {
+        static STARTED: std::time::Instant = std::time::Instant::now();
+    }
+    STARTED.with(|started| started.elapsed().as_secs_f64() * 1000.0)
+}
+
+fn tick_interval(bpm: u16) -> f64 {
+    60_000.0 / (bpm.clamp(MIN_BPM, MAX_BPM) as f64 * PPQN as f64)
+}
+
+impl ClockState {
+    /// How many ticks are due at `now` and have not been sent yet.
+    fn due_ticks(&self, now: f64) -> u64 {
+        let interval = tick_interval(self.bpm);
+        let elapsed = now - self.started_at;
+        let mut due = self.ticks_sent;
+        while (due as f64) * interval <= elapsed {
+            due += 1;
+        }
+        due - self.ticks_sent
+    }
+
+    /// Changes the tempo without moving the ticks already sent: the next tick follows the last
+    /// one by the new interval.
+    fn set_bpm(&mut self, bpm: u16) {
+        let bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        let last_tick = self.ticks_sent.saturating_sub(1) as f64;
+        self.started_at += last_tick * (tick_interval(self.bpm) - tick_interval(bpm));
+        self.bpm = bpm;
+    }
+}
+
+/// Sets the clock tempo. A running clock continues at the new tempo from the next tick.
+pub fn set_tempo(bpm: u16) {
+    CLOCK.with(|clock| clock.borrow_mut().set_bpm(bpm));
+}
+
+/// Sends Start to the output `port` and begins generating timing clock on it.
+pub fn start(port: &str) -> Result<(), MidiError> {
+    run(port, START)
+}
+
+/// Sends Continue to the output `port` and resumes generating timing clock on it.
+pub fn resume(port: &str) -> Result<(), MidiError> {
+    run(port, CONTINUE)
+}
+
+/// Sends Stop and halts the timing clock.
+pub fn stop() -> Result<(), MidiError> {
+    let port = CLOCK.with(|clock| {
+        let mut clock = clock.borrow_mut();
+        clock.interval = None;
+        clock.port.clone()
+    });
+    match port {
+        Some(port) => connection::send(&port, &[STOP]),
+        None => Ok(()),
+    }
+}
+
+fn run(port: &str, status: u8) -> Result<(), MidiError> {
+    CLOCK.with(|clock| clock.borrow_mut().interval = None);
+    connection::send(port, &[status])?;
+    CLOCK.with(|clock| {
+        let mut clock = clock.borrow_mut();
+        clock.port = Some(port.to_string());
+        clock.started_at = now();
+        clock.ticks_sent = 0;
+        clock.interval = Some(Interval::new(POLL_INTERVAL_MS, send_due_ticks));
+    });
+    Ok(())
+}
+
+/// Sends every tick that is due. Deadlines are computed from the moment the clock started, so
+/// timer jitter does not accumulate into tempo drift. The clock is not borrowed while sending,
+/// since whatever receives the ticks may read it.
+fn send_due_ticks() {
+    let Some((port, due)) = CLOCK.with(|clock| {
+        let clock = clock.borrow();
+        clock
+            .port
+            .clone()
+            .map(|port| (port, clock.due_ticks(now())))
+    }) else {
+        return;
+    };
+    for _ in 0..due {
+        if let Err(err) = connection::send(&port, &[TIMING_CLOCK]) {
+            log::error!("Error sending timing clock: {}", err);
+            return;
+        }
+        CLOCK.with(|clock| clock.borrow_mut().ticks_sent += 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::input;
+    use crate::midi::transport::{set_transport, LoopbackTransport};
+
+    fn clock(bpm: u16) -> ClockState {
+        ClockState {
+            bpm,
+            port: None,
+            started_at: 0.0,
+            ticks_sent: 0,
+            interval: None,
+        }
+    }
+
+    #[test]
+    fn ticks_follow_elapsed_time() {
+        // At 120 BPM a tick is due every 60000 / (120 * 24) ms, starting right away.
+        let interval = tick_interval(120);
+        let mut clock = clock(120);
+        assert_eq!(clock.due_ticks(0.0), 1);
+        assert_eq!(clock.due_ticks(interval - 0.1), 1);
+        assert_eq!(clock.due_ticks(interval + 0.1), 2);
+        assert_eq!(clock.due_ticks(1000.0), 49);
+        clock.ticks_sent = 49;
+        assert_eq!(clock.due_ticks(1000.0), 0);
+        assert_eq!(clock.due_ticks(49.0 * interval + 0.1), 1);
+    }
+
+    #[test]
+    fn tempo_changes_keep_the_phase() {
+        let mut clock = clock(120);
+        clock.ticks_sent = 5;
+        let last_tick = 4.0 * tick_interval(120);
+        clock.set_bpm(240);
+        assert_eq!(clock.bpm, 240);
+        assert_eq!(clock.due_ticks(last_tick + 1.0), 0);
+        assert_eq!(clock.due_ticks(last_tick + tick_interval(240) - 0.1), 0);
+        assert_eq!(clock.due_ticks(last_tick + tick_interval(240) + 0.1), 1);
+        assert_eq!(
+            clock.due_ticks(last_tick + 3.0 * tick_interval(240) + 0.1),
+            3
+        );
+
+        clock.set_bpm(1000);
+        assert_eq!(clock.bpm, MAX_BPM);
+        assert_eq!(clock.due_ticks(last_tick + tick_interval(MAX_BPM) + 0.1), 1);
+    }
+
+    #[test]
+    fn sends_due_ticks_without_holding_the_clock() {
+        let transport = LoopbackTransport::new(&["Clock"]).with_echo();
+        set_transport(transport.clone());
+        // Whatever hears the ticks may change the tempo while they are being sent.
+        let _listener = input::listen(|_, _| set_tempo(120));
+        CLOCK.with(|state| {
+            *state.borrow_mut() = ClockState {
+                port: Some("Clock".to_string()),
+                started_at: now() - 5.5 * tick_interval(120),
+                ..clock(120)
+            }
+        });
+        send_due_ticks();
+        assert_eq!(transport.sent_to("Clock"), vec![vec![TIMING_CLOCK]; 6]);
+        assert_eq!(CLOCK.with(|state| state.borrow().ticks_sent), 6);
+    }
+}
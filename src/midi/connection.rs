@@ -0,0 +1,57 @@
+//! Output connections that stay open between messages.
+//!
+//! Connections are kept per port name rather than per index, since indices shift when devices
+//! are plugged in or removed. The connections themselves belong to the current transport; this
+//! module records outgoing traffic and follows ports being unplugged and replugged: the device
+//! watcher calls `suspend` and `restore` so connections come back without waiting for a message.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use crate::midi::error::MidiError;
+use crate::midi::input;
+use crate::midi::monitor;
+use crate::midi::monitor::Direction;
+use crate::midi::transport::with_transport;
+
+thread_local! {
+    /// Ports whose connection was closed because the port went away.
+    static SUSPENDED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// Sends `message` to the output port called `name`, opening a connection if there is none yet.
+pub fn send(name: &str, message: &[u8]) -> Result<(), MidiError> {
+    with_transport(|transport| transport.send(name, message))?;
+    sent(name, message);
+    Ok(())
+}
+
+/// Hands `message` to the transport to be sent to `name` at `timestamp` on the `now()` clock.
+/// Call `sent` once it is due, so it shows up in the monitor at the right time.
+pub fn send_at(name: &str, message: &[u8], timestamp: f64) -> Result<(), MidiError> {
+    with_transport(|transport| transport.send_at(name, message, timestamp))
+}
+
+/// Records a message that went out to `name`.
+pub fn sent(name: &str, message: &[u8]) {
+    monitor::record(Direction::Out, name, message);
+    if with_transport(|transport| transport.loops_back(name)) {
+        input::receive(name, message);
+    }
+}
+
+/// Closes the connection to a port that has gone away, so it is reopened by `restore` once the
+/// port is back.
+pub fn suspend(name: &str) {
+    if with_transport(|transport| transport.disconnect(name)) {
+        SUSPENDED.with(|suspended| suspended.borrow_mut().insert(name.to_string()));
+    }
+}
+
+/// Reopens the connection to `name` if it was closed by `suspend`.
+pub fn restore(name: &str) -> Result<(), MidiError> {
+    if !SUSPENDED.with(|suspended| suspended.borrow_mut().remove(name)) {
+        return Ok(());
+    }
+    with_transport(|transport| transport.connect(name))
+}
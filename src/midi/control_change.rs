@@ -1,22 +1,27 @@
 use crate::midi::channel::MidiChannel;
 use crate::midi::fractal::FractalMidiCC;
 use crate::midi::program_change::AsU8;
+use crate::midi::value::U7;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
+pub const ALL_SOUND_OFF: u8 = 120;
+pub const RESET_ALL_CONTROLLERS: u8 = 121;
+pub const ALL_NOTES_OFF: u8 = 123;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ControlChange {
-    pub channel: u8,
-    pub control_number: u8,
-    pub value: u8,
+    pub channel: MidiChannel,
+    pub control_number: U7,
+    pub value: U7,
 }
 
 impl Default for ControlChange {
     fn default() -> Self {
         ControlChange {
-            channel: 1,
-            control_number: FractalMidiCC::SceneSelect as u8,
-            value: 0,
+            channel: MidiChannel::default(),
+            control_number: U7::from_masked(FractalMidiCC::SceneSelect as u8),
+            value: U7::default(),
         }
     }
 }
@@ -29,6 +34,10 @@ impl Display for ControlChange {
 
 impl AsU8 for ControlChange {
     fn as_u8(&self) -> Vec<u8> {
-        vec![0xB0 + self.channel as u8, self.control_number, self.value]
+        vec![
+            self.channel.status(0xB0),
+            self.control_number.get(),
+            self.value.get(),
+        ]
     }
 }
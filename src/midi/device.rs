@@ -0,0 +1,125 @@
+//! Output devices identified by port name.
+//!
+//! Port indices change whenever devices enumerate in a different order or one is unplugged, so
+//! presets refer to outputs by name and only look up the port when a message is sent.
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::midi::transport::with_transport;
+
+/// The identity of an output port: its name, plus an optional label chosen by the user.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub struct DeviceId {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl DeviceId {
+    pub fn new(name: String) -> DeviceId {
+        DeviceId { name, alias: None }
+    }
+
+    /// The alias if one is set, otherwise the port name.
+    pub fn label(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl Display for DeviceId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// A reference to an output device as stored in presets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DeviceRef {
+    Named(DeviceId),
+    /// A position in the port list, as stored by presets from before devices had names. Turned
+    /// into a `Named` reference by `resolve` once the port at that position is available.
+    Index(usize),
+}
+
+impl Default for DeviceRef {
+    fn default() -> Self {
+        DeviceRef::Index(0)
+    }
+}
+
+impl DeviceRef {
+    /// Name of the port to send to. Named devices are returned as-is, even when unplugged, so
+    /// sending fails with a clear error rather than going to another device.
+    pub fn port_name(&self) -> Option<String> {
+        match self {
+            DeviceRef::Named(id) => Some(id.name.clone()),
+            DeviceRef::Index(index) => output_names().get(*index).cloned(),
+        }
+    }
+
+    /// Like `port_name`, looking a legacy index up in `outputs` instead of listing the ports.
+    pub fn port_name_in<'a>(&'a self, outputs: &'a [String]) -> Option<&'a str> {
+        match self {
+            DeviceRef::Named(id) => Some(&id.name),
+            DeviceRef::Index(index) => outputs.get(*index).map(String::as_str),
+        }
+    }
+
+    pub fn is_available(&self, outputs: &[String]) -> bool {
+        match self {
+            DeviceRef::Named(id) => outputs.contains(&id.name),
+            DeviceRef::Index(index) => *index < outputs.len(),
+        }
+    }
+
+    /// Replaces a legacy index with the name of the port now at that index, if there is one.
+    pub fn resolve(&self, outputs: &[String]) -> DeviceRef {
+        match self {
+            DeviceRef::Index(index) => match outputs.get(*index) {
+                Some(name) => DeviceRef::Named(DeviceId::new(name.clone())),
+                None => self.clone(),
+            },
+            DeviceRef::Named(_) => self.clone(),
+        }
+    }
+
+    pub fn alias(&self) -> Option<&str> {
+        match self {
+            DeviceRef::Named(id) => id.alias.as_deref(),
+            DeviceRef::Index(_) => None,
+        }
+    }
+
+    pub fn with_alias(&self, alias: Option<String>) -> DeviceRef {
+        match self {
+            DeviceRef::Named(id) => DeviceRef::Named(DeviceId {
+                alias,
+                ..id.clone()
+            }),
+            DeviceRef::Index(_) => self.clone(),
+        }
+    }
+}
+
+impl Display for DeviceRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceRef::Named(id) => write!(f, "{}", id),
+            DeviceRef::Index(index) => write!(f, "Port {}", index + 1),
+        }
+    }
+}
+
+impl From<String> for DeviceRef {
+    fn from(name: String) -> Self {
+        DeviceRef::Named(DeviceId::new(name))
+    }
+}
+
+/// Names of the output ports currently available, in enumeration order.
+pub fn output_names() -> Vec<String> {
+    with_transport(|transport| transport.output_names())
+}
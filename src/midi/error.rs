@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Why talking to a MIDI port failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MidiError {
+    /// The MIDI system itself could not be accessed, e.g. because the browser denied access.
+    Init(String),
+    /// There is no port with this name, or at this position for devices stored as an index.
+    PortNotFound(String),
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))] // Web MIDI opens ports asynchronously.
+    Connect {
+        port: String,
+        reason: String,
+    },
+    Send {
+        port: String,
+        reason: String,
+    },
+    /// MIDI access was granted, but not for SysEx.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))] // Only browsers restrict SysEx.
+    SysExDenied(String),
+    /// A SysEx message for this port was skipped, as SysEx access has not been granted.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    SysExNotAllowed(String),
+}
+
+impl Display for MidiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiError::Init(reason) => write!(f, "MIDI is not available: {}", reason),
+            MidiError::PortNotFound(port) => write!(f, "{} is not connected", port),
+            MidiError::Connect { port, reason } => {
+                write!(f, "Could not connect to {}: {}", port, reason)
+            }
+            MidiError::Send { port, reason } => write!(f, "Could not send to {}: {}", port, reason),
+            MidiError::SysExDenied(reason) => write!(f, "SysEx access was denied: {}", reason),
+            MidiError::SysExNotAllowed(port) => {
+                write!(
+                    f,
+                    "SysEx to {} skipped, as SysEx access was not granted",
+                    port
+                )
+            }
+        }
+    }
+}
+
+impl Error for MidiError {}
+
+impl From<midir::InitError> for MidiError {
+    fn from(err: midir::InitError) -> Self {
+        MidiError::Init(err.to_string())
+    }
+}
@@ -1,6 +1,8 @@
 use enum_display::EnumDisplay;
 use enum_iterator::Sequence;
 
+pub mod sysex;
+
 #[derive(Debug, Clone, PartialEq, Sequence, EnumDisplay, Copy)]
 pub enum FractalMidiCC {
     InputVolume = 10,
@@ -124,6 +126,13 @@ pub enum FractalMidiCC {
     Wahwah2XY = 119,
 }
 
+impl FractalMidiCC {
+    /// The Fractal function assigned to a CC number by default, if any.
+    pub fn from_number(number: u8) -> Option<FractalMidiCC> {
+        enum_iterator::all::<FractalMidiCC>().find(|cc| *cc as u8 == number)
+    }
+}
+
 impl From<FractalMidiCC> for u8 {
     fn from(cc: FractalMidiCC) -> u8 {
         cc as u8
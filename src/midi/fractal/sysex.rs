@@ -0,0 +1,521 @@
+//! Codec for the Fractal Audio "MIDI for 3rd party devices" SysEx protocol used by the
+//! Axe-Fx III, FM3 and FM9.
+//!
+//! Every message has the shape `F0 00 01 74 <model> <function> <data...> <checksum> F7`, where
+//! the checksum is the XOR of all bytes from `F0` up to the last data byte, masked to 7 bits.
+//! The only exception is the tuner info message, which the unit sends without a checksum.
+//!
+//! The protocol has no preset recall function; presets are selected with Program Change.
+
+use enum_display::EnumDisplay;
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::midi::program_change::AsU8;
+use crate::midi::sysex::{SYSEX_END, SYSEX_START};
+
+pub const FRACTAL_MANUFACTURER_ID: [u8; 3] = [0x00, 0x01, 0x74];
+
+const QUERY: u8 = 0x7F;
+const PATCH_NAME_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Sequence, EnumDisplay, Copy, Serialize, Deserialize)]
+pub enum FractalModel {
+    AxeFxIII = 0x10,
+    FM3 = 0x11,
+    FM9 = 0x12,
+}
+
+impl TryFrom<u8> for FractalModel {
+    type Error = FractalError;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        enum_iterator::all::<FractalModel>()
+            .find(|m| *m as u8 == id)
+            .ok_or(FractalError::UnknownModel(id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub enum FractalFunction {
+    Bypass = 0x0A,
+    Channel = 0x0B,
+    Scene = 0x0C,
+    PatchName = 0x0D,
+    TapTempo = 0x10,
+    Tuner = 0x11,
+    Tempo = 0x14,
+}
+
+impl TryFrom<u8> for FractalFunction {
+    type Error = FractalError;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        match id {
+            0x0A => Ok(FractalFunction::Bypass),
+            0x0B => Ok(FractalFunction::Channel),
+            0x0C => Ok(FractalFunction::Scene),
+            0x0D => Ok(FractalFunction::PatchName),
+            0x10 => Ok(FractalFunction::TapTempo),
+            0x11 => Ok(FractalFunction::Tuner),
+            0x14 => Ok(FractalFunction::Tempo),
+            _ => Err(FractalError::UnknownFunction(id)),
+        }
+    }
+}
+
+/// A Fractal function. Set commands and the unit's replies share the same layout, so a parsed
+/// reply to `QueryScene` comes back as `Scene`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FractalCommand {
+    /// Queries the name of a preset, or of the current preset when `None`.
+    QueryPreset(Option<u16>),
+    Preset {
+        number: u16,
+        name: String,
+    },
+    QueryScene,
+    Scene(u8),
+    QueryBypass {
+        block: u16,
+    },
+    Bypass {
+        block: u16,
+        bypassed: bool,
+    },
+    QueryChannel {
+        block: u16,
+    },
+    Channel {
+        block: u16,
+        channel: u8,
+    },
+    QueryTempo,
+    Tempo(u16),
+    TapTempo,
+    Tuner(bool),
+    TunerInfo {
+        note: u8,
+        string: u8,
+        cents: u8,
+    },
+}
+
+impl FractalCommand {
+    pub fn function(&self) -> FractalFunction {
+        match self {
+            FractalCommand::QueryPreset(_) | FractalCommand::Preset { .. } => {
+                FractalFunction::PatchName
+            }
+            FractalCommand::QueryScene | FractalCommand::Scene(_) => FractalFunction::Scene,
+            FractalCommand::QueryBypass { .. } | FractalCommand::Bypass { .. } => {
+                FractalFunction::Bypass
+            }
+            FractalCommand::QueryChannel { .. } | FractalCommand::Channel { .. } => {
+                FractalFunction::Channel
+            }
+            FractalCommand::QueryTempo | FractalCommand::Tempo(_) => FractalFunction::Tempo,
+            FractalCommand::TapTempo => FractalFunction::TapTempo,
+            FractalCommand::Tuner(_) | FractalCommand::TunerInfo { .. } => FractalFunction::Tuner,
+        }
+    }
+
+    fn data(&self) -> Vec<u8> {
+        match self {
+            FractalCommand::QueryPreset(None) => vec![QUERY, QUERY],
+            FractalCommand::QueryPreset(Some(number)) => split_14bit(*number).to_vec(),
+            FractalCommand::Preset { number, name } => {
+                let mut data = split_14bit(*number).to_vec();
+                data.extend(
+                    name.chars()
+                        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+                        .chain(std::iter::repeat(b' '))
+                        .take(PATCH_NAME_LENGTH),
+                );
+                data
+            }
+            FractalCommand::QueryScene => vec![QUERY],
+            FractalCommand::Scene(scene) => vec![*scene],
+            FractalCommand::QueryBypass { block } => [&split_14bit(*block)[..], &[QUERY]].concat(),
+            FractalCommand::Bypass { block, bypassed } => {
+                [&split_14bit(*block)[..], &[*bypassed as u8]].concat()
+            }
+            FractalCommand::QueryChannel { block } => [&split_14bit(*block)[..], &[QUERY]].concat(),
+            FractalCommand::Channel { block, channel } => {
+                [&split_14bit(*block)[..], &[*channel]].concat()
+            }
+            FractalCommand::QueryTempo => vec![QUERY, QUERY],
+            FractalCommand::Tempo(bpm) => split_14bit(*bpm).to_vec(),
+            FractalCommand::TapTempo => vec![],
+            FractalCommand::Tuner(on) => vec![*on as u8],
+            FractalCommand::TunerInfo {
+                note,
+                string,
+                cents,
+            } => vec![*note, *string, *cents],
+        }
+    }
+
+    fn has_checksum(&self) -> bool {
+        !matches!(self, FractalCommand::TunerInfo { .. })
+    }
+
+    fn from_data(function: FractalFunction, data: &[u8]) -> Result<Self, FractalError> {
+        let command = match (function, data) {
+            (FractalFunction::PatchName, [QUERY, QUERY]) => FractalCommand::QueryPreset(None),
+            (FractalFunction::PatchName, [lsb, msb]) => {
+                FractalCommand::QueryPreset(Some(join_14bit(*lsb, *msb)))
+            }
+            (FractalFunction::PatchName, [lsb, msb, name @ ..]) => FractalCommand::Preset {
+                number: join_14bit(*lsb, *msb),
+                name: String::from_utf8_lossy(name)
+                    .trim_end_matches([' ', '\0'])
+                    .to_string(),
+            },
+            (FractalFunction::Scene, [QUERY]) => FractalCommand::QueryScene,
+            (FractalFunction::Scene, [scene]) => FractalCommand::Scene(*scene),
+            (FractalFunction::Bypass, [lsb, msb, QUERY]) => FractalCommand::QueryBypass {
+                block: join_14bit(*lsb, *msb),
+            },
+            (FractalFunction::Bypass, [lsb, msb, state]) => FractalCommand::Bypass {
+                block: join_14bit(*lsb, *msb),
+                bypassed: *state != 0,
+            },
+            (FractalFunction::Channel, [lsb, msb, QUERY]) => FractalCommand::QueryChannel {
+                block: join_14bit(*lsb, *msb),
+            },
+            (FractalFunction::Channel, [lsb, msb, channel]) => FractalCommand::Channel {
+                block: join_14bit(*lsb, *msb),
+                channel: *channel,
+            },
+            (FractalFunction::Tempo, [QUERY, QUERY]) => FractalCommand::QueryTempo,
+            (FractalFunction::Tempo, [lsb, msb]) => FractalCommand::Tempo(join_14bit(*lsb, *msb)),
+            (FractalFunction::TapTempo, []) => FractalCommand::TapTempo,
+            (FractalFunction::Tuner, [on]) => FractalCommand::Tuner(*on != 0),
+            (FractalFunction::Tuner, [note, string, cents]) => FractalCommand::TunerInfo {
+                note: *note,
+                string: *string,
+                cents: *cents,
+            },
+            _ => return Err(FractalError::InvalidLength(function as u8)),
+        };
+        Ok(command)
+    }
+}
+
+impl Display for FractalCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FractalCommand::QueryPreset(None) => write!(f, "Query current preset"),
+            FractalCommand::QueryPreset(Some(number)) => write!(f, "Query preset {}", number),
+            FractalCommand::Preset { number, name } => write!(f, "Preset {}: {}", number, name),
+            FractalCommand::QueryScene => write!(f, "Query scene"),
+            FractalCommand::Scene(scene) => write!(f, "Scene {}", scene + 1),
+            FractalCommand::QueryBypass { block } => write!(f, "Query bypass of block {}", block),
+            FractalCommand::Bypass { block, bypassed } => write!(
+                f,
+                "Block {} {}",
+                block,
+                if *bypassed { "bypassed" } else { "engaged" }
+            ),
+            FractalCommand::QueryChannel { block } => {
+                write!(f, "Query channel of block {}", block)
+            }
+            FractalCommand::Channel { block, channel } => {
+                let letter = b'A'.checked_add(*channel).map_or('?', char::from);
+                write!(f, "Block {} channel {}", block, letter)
+            }
+            FractalCommand::QueryTempo => write!(f, "Query tempo"),
+            FractalCommand::Tempo(bpm) => write!(f, "Tempo {} BPM", bpm),
+            FractalCommand::TapTempo => write!(f, "Tap tempo"),
+            FractalCommand::Tuner(on) => write!(f, "Tuner {}", if *on { "on" } else { "off" }),
+            FractalCommand::TunerInfo {
+                note,
+                string,
+                cents,
+            } => write!(
+                f,
+                "Tuner note {} string {} cents {}",
+                note,
+                string + 1,
+                cents
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FractalError {
+    NotFractal,
+    UnknownModel(u8),
+    UnknownFunction(u8),
+    InvalidLength(u8),
+    InvalidDataByte { index: usize, byte: u8 },
+    InvalidChecksum { expected: u8, actual: u8 },
+}
+
+impl Display for FractalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FractalError::NotFractal => write!(f, "Not a Fractal Audio SysEx message"),
+            FractalError::UnknownModel(id) => write!(f, "Unknown Fractal model {:02X}", id),
+            FractalError::UnknownFunction(id) => write!(f, "Unknown Fractal function {:02X}", id),
+            FractalError::InvalidLength(id) => {
+                write!(f, "Unexpected data length for Fractal function {:02X}", id)
+            }
+            FractalError::InvalidDataByte { index, byte } => {
+                write!(f, "Byte {} ({:02X}) is not a 7-bit data byte", index, byte)
+            }
+            FractalError::InvalidChecksum { expected, actual } => write!(
+                f,
+                "Invalid checksum {:02X}, expected {:02X}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl Error for FractalError {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FractalMessage {
+    pub model: FractalModel,
+    pub command: FractalCommand,
+}
+
+impl Default for FractalMessage {
+    fn default() -> Self {
+        FractalMessage {
+            model: FractalModel::AxeFxIII,
+            command: FractalCommand::Scene(0),
+        }
+    }
+}
+
+impl FractalMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![SYSEX_START];
+        bytes.extend_from_slice(&FRACTAL_MANUFACTURER_ID);
+        bytes.push(self.model as u8);
+        bytes.push(self.command.function() as u8);
+        bytes.extend(self.command.data().iter().map(|b| b & 0x7F));
+        if self.command.has_checksum() {
+            bytes.push(checksum(&bytes));
+        }
+        bytes.push(SYSEX_END);
+        bytes
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<FractalMessage, FractalError> {
+        if bytes.len() < 7
+            || bytes[0] != SYSEX_START
+            || bytes[1..4] != FRACTAL_MANUFACTURER_ID
+            || bytes[bytes.len() - 1] != SYSEX_END
+        {
+            return Err(FractalError::NotFractal);
+        }
+        if let Some(i) = bytes[1..bytes.len() - 1].iter().position(|b| *b > 0x7F) {
+            return Err(FractalError::InvalidDataByte {
+                index: i + 1,
+                byte: bytes[i + 1],
+            });
+        }
+        let model = FractalModel::try_from(bytes[4])?;
+        let function = FractalFunction::try_from(bytes[5])?;
+        let body = &bytes[6..bytes.len() - 1];
+
+        // Tuner info is the only message sent without a checksum.
+        if function == FractalFunction::Tuner && body.len() == 3 {
+            let command = FractalCommand::from_data(function, body)?;
+            return Ok(FractalMessage { model, command });
+        }
+
+        let (data, actual) = match body.split_last() {
+            Some((actual, data)) => (data, *actual),
+            None => return Err(FractalError::InvalidLength(function as u8)),
+        };
+        let expected = checksum(&bytes[..bytes.len() - 2]);
+        if expected != actual {
+            return Err(FractalError::InvalidChecksum { expected, actual });
+        }
+        let command = FractalCommand::from_data(function, data)?;
+        Ok(FractalMessage { model, command })
+    }
+}
+
+impl Display for FractalMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.model, self.command)
+    }
+}
+
+impl AsU8 for FractalMessage {
+    fn as_u8(&self) -> Vec<u8> {
+        self.encode()
+    }
+}
+
+/// XOR of all given bytes, masked to 7 bits.
+pub fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |acc, b| acc ^ b) & 0x7F
+}
+
+fn split_14bit(value: u16) -> [u8; 2] {
+    [(value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8]
+}
+
+fn join_14bit(lsb: u8, msb: u8) -> u16 {
+    (lsb as u16 & 0x7F) | ((msb as u16 & 0x7F) << 7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a dump written as hex bytes separated by spaces.
+    fn dump(hex: &str) -> Vec<u8> {
+        hex.split_whitespace()
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect()
+    }
+
+    fn message(model: FractalModel, command: FractalCommand) -> FractalMessage {
+        FractalMessage { model, command }
+    }
+
+    /// Dumps of the messages in the Fractal Audio 3rd party MIDI specification, with the
+    /// checksums worked out by hand.
+    fn reference() -> Vec<(&'static str, FractalMessage)> {
+        use FractalCommand::*;
+        use FractalModel::*;
+        vec![
+            ("F0 00 01 74 10 0C 01 18 F7", message(AxeFxIII, Scene(1))),
+            ("F0 00 01 74 10 0C 7F 66 F7", message(AxeFxIII, QueryScene)),
+            (
+                "F0 00 01 74 10 0A 3A 00 01 24 F7",
+                message(
+                    AxeFxIII,
+                    Bypass {
+                        block: 58,
+                        bypassed: true,
+                    },
+                ),
+            ),
+            (
+                "F0 00 01 74 10 0B 3A 00 7F 5B F7",
+                message(AxeFxIII, QueryChannel { block: 58 }),
+            ),
+            (
+                "F0 00 01 74 12 0B 3A 00 01 27 F7",
+                message(
+                    FM9,
+                    Channel {
+                        block: 58,
+                        channel: 1,
+                    },
+                ),
+            ),
+            (
+                "F0 00 01 74 10 14 78 00 79 F7",
+                message(AxeFxIII, Tempo(120)),
+            ),
+            (
+                "F0 00 01 74 10 14 7F 7F 01 F7",
+                message(AxeFxIII, QueryTempo),
+            ),
+            ("F0 00 01 74 10 10 05 F7", message(AxeFxIII, TapTempo)),
+            ("F0 00 01 74 10 11 01 05 F7", message(AxeFxIII, Tuner(true))),
+            (
+                "F0 00 01 74 10 0D 7F 7F 18 F7",
+                message(AxeFxIII, QueryPreset(None)),
+            ),
+            (
+                "F0 00 01 74 11 0D 05 01 43 6C 65 61 6E 20 20 20 20 20 20 20 20 20 20 20 20 20 20 \
+                 20 20 20 20 20 20 20 20 20 20 20 20 20 78 F7",
+                message(
+                    FM3,
+                    Preset {
+                        number: 133,
+                        name: "Clean".to_string(),
+                    },
+                ),
+            ),
+        ]
+    }
+
+    #[test]
+    fn encodes_reference_dumps() {
+        for (hex, message) in reference() {
+            assert_eq!(message.encode(), dump(hex), "{}", message);
+        }
+    }
+
+    #[test]
+    fn parses_reference_dumps() {
+        for (hex, message) in reference() {
+            assert_eq!(FractalMessage::parse(&dump(hex)), Ok(message), "{}", hex);
+        }
+    }
+
+    #[test]
+    fn parses_tuner_info_without_checksum() {
+        assert_eq!(
+            FractalMessage::parse(&dump("F0 00 01 74 10 11 09 02 3F F7")),
+            Ok(message(
+                FractalModel::AxeFxIII,
+                FractalCommand::TunerInfo {
+                    note: 9,
+                    string: 2,
+                    cents: 63,
+                },
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        assert_eq!(
+            FractalMessage::parse(&dump("F0 00 01 74 10 0C 01 19 F7")),
+            Err(FractalError::InvalidChecksum {
+                expected: 0x18,
+                actual: 0x19,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bytes_that_are_not_7_bit() {
+        assert_eq!(
+            FractalMessage::parse(&dump("F0 00 01 74 10 0B 3A 00 C8 A0 F7")),
+            Err(FractalError::InvalidDataByte {
+                index: 8,
+                byte: 0xC8,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_other_manufacturers_and_unknown_ids() {
+        assert_eq!(
+            FractalMessage::parse(&dump("F0 7E 7F 06 01 F7 F7")),
+            Err(FractalError::NotFractal)
+        );
+        assert_eq!(
+            FractalMessage::parse(&dump("F0 00 01 74 05 0C 01 18 F7")),
+            Err(FractalError::UnknownModel(0x05))
+        );
+        assert_eq!(
+            FractalMessage::parse(&dump("F0 00 01 74 10 01 01 18 F7")),
+            Err(FractalError::UnknownFunction(0x01))
+        );
+    }
+
+    #[test]
+    fn displays_any_channel_without_panicking() {
+        let channel = |channel| FractalCommand::Channel { block: 58, channel }.to_string();
+        assert_eq!(channel(1), "Block 58 channel B");
+        assert_eq!(channel(u8::MAX), "Block 58 channel ?");
+    }
+}
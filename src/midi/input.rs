@@ -0,0 +1,172 @@
+//! Input ports the app listens on.
+//!
+//! Like outputs, inputs are kept open per port name. Everything received on an open input is
+//! recorded by the monitor and handed to the current listeners. In the browser inputs are opened
+//! through Web MIDI, elsewhere through midir.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+use crate::midi::error::MidiError;
+use crate::midi::midi_message::MidiMessage;
+use crate::midi::monitor;
+use crate::midi::monitor::Direction;
+use crate::midi::parser::MidiParser;
+#[cfg(target_arch = "wasm32")]
+use crate::midi::web_midi;
+
+#[cfg(not(target_arch = "wasm32"))]
+const CLIENT_NAME: &str = "My MIDI Input";
+#[cfg(not(target_arch = "wasm32"))]
+const CONNECTION_NAME: &str = "fractal-midi-rs";
+
+type Callback = Rc<dyn Fn(&str, &MidiMessage)>;
+
+#[cfg(target_arch = "wasm32")]
+type Connection = web_midi::InputConnection;
+#[cfg(not(target_arch = "wasm32"))]
+type Connection = MidiInputConnection<()>;
+
+thread_local! {
+    static CONNECTIONS: RefCell<HashMap<String, Connection>> =
+        RefCell::new(HashMap::new());
+    /// Inputs that were open when their port went away.
+    static SUSPENDED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+    static LISTENERS: RefCell<Vec<(u64, Callback)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_LISTENER: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Keeps a listener registered with `listen` until it is dropped.
+#[derive(Debug)]
+pub struct Listener(u64);
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        LISTENERS.with(|listeners| listeners.borrow_mut().retain(|(id, _)| *id != self.0));
+    }
+}
+
+/// Calls `on_message` with the port name and message for everything received on an open input,
+/// for as long as the returned `Listener` is kept.
+pub fn listen(on_message: impl Fn(&str, &MidiMessage) + 'static) -> Listener {
+    let id = NEXT_LISTENER.with(|next| next.replace(next.get() + 1));
+    LISTENERS.with(|listeners| listeners.borrow_mut().push((id, Rc::new(on_message))));
+    Listener(id)
+}
+
+/// Names of the input ports currently available, in enumeration order.
+#[cfg(target_arch = "wasm32")]
+pub fn input_names() -> Vec<String> {
+    web_midi::input_names()
+}
+
+/// Names of the input ports currently available, in enumeration order.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn input_names() -> Vec<String> {
+    let Ok(midi_in) = MidiInput::new(CLIENT_NAME) else {
+        return vec![];
+    };
+    midi_in
+        .ports()
+        .iter()
+        .filter_map(|port| midi_in.port_name(port).ok())
+        .collect()
+}
+
+/// Starts listening on the input port called `name`. Does nothing if it is already open.
+pub fn open(name: &str) -> Result<(), MidiError> {
+    if is_open(name) {
+        return Ok(());
+    }
+    let connection = connect(name)?;
+    CONNECTIONS.with(|connections| {
+        connections
+            .borrow_mut()
+            .insert(name.to_string(), connection)
+    });
+    Ok(())
+}
+
+pub fn close(name: &str) {
+    SUSPENDED.with(|suspended| suspended.borrow_mut().remove(name));
+    CONNECTIONS.with(|connections| {
+        if let Some(connection) = connections.borrow_mut().remove(name) {
+            connection.close();
+        }
+    })
+}
+
+/// Whether the user has opened `name`, including inputs waiting for their port to come back.
+pub fn is_open(name: &str) -> bool {
+    CONNECTIONS.with(|connections| connections.borrow().contains_key(name))
+        || SUSPENDED.with(|suspended| suspended.borrow().contains(name))
+}
+
+/// Closes an open input whose port has gone away, so it is reopened by `restore`.
+pub fn suspend(name: &str) {
+    CONNECTIONS.with(|connections| {
+        if let Some(connection) = connections.borrow_mut().remove(name) {
+            connection.close();
+            SUSPENDED.with(|suspended| suspended.borrow_mut().insert(name.to_string()));
+        }
+    })
+}
+
+/// Reopens `name` if it was closed by `suspend`.
+pub fn restore(name: &str) -> Result<(), MidiError> {
+    if !SUSPENDED.with(|suspended| suspended.borrow_mut().remove(name)) {
+        return Ok(());
+    }
+    open(name)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn connect(name: &str) -> Result<Connection, MidiError> {
+    let port_name = name.to_string();
+    web_midi::connect_input(name, move |bytes| receive(&port_name, bytes))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn connect(name: &str) -> Result<Connection, MidiError> {
+    let mut midi_in = MidiInput::new(CLIENT_NAME)?;
+    midi_in.ignore(Ignore::ActiveSense);
+    let port = midi_in
+        .ports()
+        .into_iter()
+        .find(|port| midi_in.port_name(port).ok().as_deref() == Some(name))
+        .ok_or_else(|| MidiError::PortNotFound(name.to_string()))?;
+    let port_name = name.to_string();
+    midi_in
+        .connect(
+            &port,
+            CONNECTION_NAME,
+            move |_, bytes, _| receive(&port_name, bytes),
+            (),
+        )
+        .map_err(|err| MidiError::Connect {
+            port: name.to_string(),
+            reason: err.to_string(),
+        })
+}
+
+/// Handles a message arriving on the input port called `port`.
+pub fn receive(port: &str, bytes: &[u8]) {
+    monitor::record(Direction::In, port, bytes);
+    // Listeners may register or drop listeners themselves, so call them on a copy of the list.
+    let listeners: Vec<_> = LISTENERS.with(|listeners| {
+        listeners
+            .borrow()
+            .iter()
+            .map(|(_, listener)| listener.clone())
+            .collect()
+    });
+    for message in MidiParser::parse(bytes) {
+        for listener in listeners.iter() {
+            listener(port, &message);
+        }
+    }
+}
@@ -1,10 +1,11 @@
+use crate::midi::device::DeviceRef;
 use crate::midi::midi_message::MidiMessage;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MidiBlock {
     pub id: usize,
     pub name: String,
-    pub device_index: usize,
+    pub device: DeviceRef,
     pub messages: Vec<MidiMessage>,
 }
 
@@ -13,7 +14,7 @@ impl MidiBlock {
         MidiBlock {
             id,
             name: "New MidiConfig".to_string(),
-            device_index: 0,
+            device: DeviceRef::default(),
             messages: Vec::new(),
         }
     }
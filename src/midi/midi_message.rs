@@ -1,12 +1,27 @@
-use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::thread::sleep;
 
-use midir::{MidiOutput, MidiOutputConnection, MidiOutputPorts};
 use serde::{Deserialize, Serialize};
 
-use crate::midi::control_change::ControlChange;
+use crate::midi::bank_select::{BankProgramChange, BankSelectMode};
+use crate::midi::channel::MidiChannel;
+use crate::midi::clock;
+use crate::midi::clock::SongPosition;
+use crate::midi::connection;
+use crate::midi::control_change::{
+    ControlChange, ALL_NOTES_OFF, ALL_SOUND_OFF, RESET_ALL_CONTROLLERS,
+};
+use crate::midi::device::{output_names, DeviceRef};
+use crate::midi::error::MidiError;
+use crate::midi::fractal::sysex::FractalMessage;
+use crate::midi::note::{NoteOff, NoteOn};
+use crate::midi::parameter_number::{ControlChange14, Nrpn, Rpn};
+use crate::midi::pitch_bend::PitchBend;
+use crate::midi::pressure::{ChannelPressure, PolyPressure};
 use crate::midi::program_change::{AsU8, ProgramChange};
+use crate::midi::scheduler;
+use crate::midi::scheduler::SendFailure;
+use crate::midi::sysex::SysEx;
+use crate::midi::value::{U14, U7};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MidiMessage {
@@ -14,6 +29,23 @@ pub enum MidiMessage {
     Empty,
     ProgramChange(ProgramChange),
     ControlChange(ControlChange),
+    NoteOn(NoteOn),
+    NoteOff(NoteOff),
+    SysEx(SysEx),
+    Fractal(FractalMessage),
+    PitchBend(PitchBend),
+    ChannelPressure(ChannelPressure),
+    PolyPressure(PolyPressure),
+    Nrpn(Nrpn),
+    Rpn(Rpn),
+    ControlChange14(ControlChange14),
+    BankProgramChange(BankProgramChange),
+    SongPosition(SongPosition),
+    Tempo(u16),
+    Start,
+    Stop,
+    Continue,
+    TimingClock,
 }
 
 impl MidiMessage {
@@ -23,8 +55,207 @@ impl MidiMessage {
             MidiMessage::Delay(0),
             MidiMessage::ProgramChange(ProgramChange::default()),
             MidiMessage::ControlChange(ControlChange::default()),
+            MidiMessage::NoteOn(NoteOn::default()),
+            MidiMessage::NoteOff(NoteOff::default()),
+            MidiMessage::SysEx(SysEx::default()),
+            MidiMessage::Fractal(FractalMessage::default()),
+            MidiMessage::PitchBend(PitchBend::default()),
+            MidiMessage::ChannelPressure(ChannelPressure::default()),
+            MidiMessage::PolyPressure(PolyPressure::default()),
+            MidiMessage::Nrpn(Nrpn::default()),
+            MidiMessage::Rpn(Rpn::default()),
+            MidiMessage::ControlChange14(ControlChange14::default()),
+            MidiMessage::BankProgramChange(BankProgramChange::default()),
+            MidiMessage::SongPosition(SongPosition::default()),
+            MidiMessage::Tempo(clock::DEFAULT_BPM),
+            MidiMessage::Start,
+            MidiMessage::Stop,
+            MidiMessage::Continue,
         ]
     }
+
+    /// Name of the kind of message, e.g. "Control Change".
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MidiMessage::Delay(_) => "Delay",
+            MidiMessage::Empty => "Empty",
+            MidiMessage::ProgramChange(_) => "Program Change",
+            MidiMessage::ControlChange(_) => "Control Change",
+            MidiMessage::NoteOn(_) => "Note On",
+            MidiMessage::NoteOff(_) => "Note Off",
+            MidiMessage::SysEx(_) => "SysEx",
+            MidiMessage::Fractal(_) => "Fractal SysEx",
+            MidiMessage::PitchBend(_) => "Pitch Bend",
+            MidiMessage::ChannelPressure(_) => "Channel Pressure",
+            MidiMessage::PolyPressure(_) => "Poly Pressure",
+            MidiMessage::Nrpn(_) => "NRPN",
+            MidiMessage::Rpn(_) => "RPN",
+            MidiMessage::ControlChange14(_) => "Control Change (14-bit)",
+            MidiMessage::BankProgramChange(_) => "Bank + Program Change",
+            MidiMessage::SongPosition(_) => "Song Position",
+            MidiMessage::Tempo(_) => "Clock Tempo",
+            MidiMessage::Start => "Start",
+            MidiMessage::Stop => "Stop",
+            MidiMessage::Continue => "Continue",
+            MidiMessage::TimingClock => "Timing Clock",
+        }
+    }
+
+    /// Whether the message is sent as SysEx, which browsers only allow with permission.
+    pub fn is_sysex(&self) -> bool {
+        matches!(self, MidiMessage::SysEx(_) | MidiMessage::Fractal(_))
+    }
+
+    /// The channel of a channel message.
+    pub fn channel(&self) -> Option<MidiChannel> {
+        match self {
+            MidiMessage::ProgramChange(m) => Some(m.channel),
+            MidiMessage::ControlChange(m) => Some(m.channel),
+            MidiMessage::NoteOn(m) => Some(m.channel),
+            MidiMessage::NoteOff(m) => Some(m.channel),
+            MidiMessage::PitchBend(m) => Some(m.channel),
+            MidiMessage::ChannelPressure(m) => Some(m.channel),
+            MidiMessage::PolyPressure(m) => Some(m.channel),
+            MidiMessage::Nrpn(m) => Some(m.channel),
+            MidiMessage::Rpn(m) => Some(m.channel),
+            MidiMessage::ControlChange14(m) => Some(m.channel),
+            MidiMessage::BankProgramChange(m) => Some(m.channel),
+            _ => None,
+        }
+    }
+
+    /// The same message on another channel. Messages without a channel are returned as-is.
+    pub fn with_channel(self, channel: MidiChannel) -> MidiMessage {
+        match self {
+            MidiMessage::ProgramChange(m) => {
+                MidiMessage::ProgramChange(ProgramChange { channel, ..m })
+            }
+            MidiMessage::ControlChange(m) => {
+                MidiMessage::ControlChange(ControlChange { channel, ..m })
+            }
+            MidiMessage::NoteOn(m) => MidiMessage::NoteOn(NoteOn { channel, ..m }),
+            MidiMessage::NoteOff(m) => MidiMessage::NoteOff(NoteOff { channel, ..m }),
+            MidiMessage::PitchBend(m) => MidiMessage::PitchBend(PitchBend { channel, ..m }),
+            MidiMessage::ChannelPressure(m) => {
+                MidiMessage::ChannelPressure(ChannelPressure { channel, ..m })
+            }
+            MidiMessage::PolyPressure(m) => {
+                MidiMessage::PolyPressure(PolyPressure { channel, ..m })
+            }
+            MidiMessage::Nrpn(m) => MidiMessage::Nrpn(Nrpn { channel, ..m }),
+            MidiMessage::Rpn(m) => MidiMessage::Rpn(Rpn { channel, ..m }),
+            MidiMessage::ControlChange14(m) => {
+                MidiMessage::ControlChange14(ControlChange14 { channel, ..m })
+            }
+            MidiMessage::BankProgramChange(m) => {
+                MidiMessage::BankProgramChange(BankProgramChange { channel, ..m })
+            }
+            message => message,
+        }
+    }
+
+    /// What MIDI learn turns this message into when `received` comes in, or `None` if `received`
+    /// is not of the same kind. Channel, number and value are taken from `received`; a bank
+    /// program change keeps its bank and a 14-bit control change its LSB. An empty entry takes
+    /// any channel message.
+    pub fn learn(&self, received: &MidiMessage) -> Option<MidiMessage> {
+        let learned = match (self, received) {
+            (MidiMessage::Empty, received) => received.clone(),
+            (MidiMessage::ProgramChange(_), MidiMessage::ProgramChange(_))
+            | (MidiMessage::ControlChange(_), MidiMessage::ControlChange(_))
+            | (MidiMessage::NoteOn(_), MidiMessage::NoteOn(_))
+            | (MidiMessage::NoteOff(_), MidiMessage::NoteOff(_))
+            | (MidiMessage::PitchBend(_), MidiMessage::PitchBend(_))
+            | (MidiMessage::ChannelPressure(_), MidiMessage::ChannelPressure(_))
+            | (MidiMessage::PolyPressure(_), MidiMessage::PolyPressure(_)) => received.clone(),
+            (MidiMessage::BankProgramChange(current), MidiMessage::ProgramChange(pc)) => {
+                MidiMessage::BankProgramChange(BankProgramChange {
+                    channel: pc.channel,
+                    ..current.with_program(pc.program.get())
+                })
+            }
+            (MidiMessage::ControlChange14(current), MidiMessage::ControlChange(cc)) => {
+                let value = U14::from_parts(cc.value.get(), current.value.lsb());
+                MidiMessage::ControlChange14(
+                    ControlChange14::new(cc.channel, cc.control_number, value).ok()?,
+                )
+            }
+            _ => return None,
+        };
+        learned.channel().map(|_| learned)
+    }
+
+    /// Bytes this message puts on the wire. Delays, empty entries and clock tempo changes have
+    /// no bytes of their own.
+    pub fn as_u8_with(&self, bank_select: BankSelectMode) -> Vec<u8> {
+        match self {
+            MidiMessage::Delay(_) | MidiMessage::Empty | MidiMessage::Tempo(_) => vec![],
+            MidiMessage::ProgramChange(pc) => pc.as_u8(),
+            MidiMessage::ControlChange(cc) => cc.as_u8(),
+            MidiMessage::NoteOn(note) => note.as_u8(),
+            MidiMessage::NoteOff(note) => note.as_u8(),
+            MidiMessage::SysEx(sysex) => sysex.as_u8(),
+            MidiMessage::Fractal(fractal) => fractal.as_u8(),
+            MidiMessage::PitchBend(message) => message.as_u8(),
+            MidiMessage::ChannelPressure(message) => message.as_u8(),
+            MidiMessage::PolyPressure(message) => message.as_u8(),
+            MidiMessage::Nrpn(message) => message.as_u8(),
+            MidiMessage::Rpn(message) => message.as_u8(),
+            MidiMessage::ControlChange14(message) => message.as_u8(),
+            MidiMessage::BankProgramChange(pc) => pc.as_u8_with(bank_select),
+            MidiMessage::SongPosition(message) => message.as_u8(),
+            MidiMessage::Start => vec![clock::START],
+            MidiMessage::Stop => vec![clock::STOP],
+            MidiMessage::Continue => vec![clock::CONTINUE],
+            MidiMessage::TimingClock => vec![clock::TIMING_CLOCK],
+        }
+    }
+}
+
+/// A step of a preset: a message and, optionally, the output it is sent to instead of the
+/// preset's own device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(from = "StoredPresetMessage")]
+pub struct PresetMessage {
+    pub message: MidiMessage,
+    pub device: Option<DeviceRef>,
+}
+
+impl PresetMessage {
+    /// The output this message goes to in a preset that targets `preset_device`.
+    pub fn device_or(&self, preset_device: &DeviceRef) -> DeviceRef {
+        self.device.clone().unwrap_or_else(|| preset_device.clone())
+    }
+}
+
+impl From<MidiMessage> for PresetMessage {
+    fn from(message: MidiMessage) -> Self {
+        PresetMessage {
+            message,
+            device: None,
+        }
+    }
+}
+
+/// Presets used to store bare messages; those still load, without a device override.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPresetMessage {
+    Targeted {
+        message: MidiMessage,
+        #[serde(default)]
+        device: Option<DeviceRef>,
+    },
+    Plain(MidiMessage),
+}
+
+impl From<StoredPresetMessage> for PresetMessage {
+    fn from(stored: StoredPresetMessage) -> Self {
+        match stored {
+            StoredPresetMessage::Targeted { message, device } => PresetMessage { message, device },
+            StoredPresetMessage::Plain(message) => PresetMessage::from(message),
+        }
+    }
 }
 
 impl Default for MidiMessage {
@@ -40,6 +271,23 @@ impl Display for MidiMessage {
             MidiMessage::Delay(delay) => write!(f, "Delay: {} ms", delay),
             MidiMessage::ProgramChange(pc) => write!(f, "{:?}", pc),
             MidiMessage::ControlChange(cc) => write!(f, "{:?}", cc),
+            MidiMessage::NoteOn(note) => write!(f, "{:?}", note),
+            MidiMessage::NoteOff(note) => write!(f, "{:?}", note),
+            MidiMessage::SysEx(sysex) => write!(f, "{}", sysex),
+            MidiMessage::Fractal(fractal) => write!(f, "{}", fractal),
+            MidiMessage::PitchBend(pitch_bend) => write!(f, "{:?}", pitch_bend),
+            MidiMessage::ChannelPressure(pressure) => write!(f, "{:?}", pressure),
+            MidiMessage::PolyPressure(pressure) => write!(f, "{:?}", pressure),
+            MidiMessage::Nrpn(nrpn) => write!(f, "{:?}", nrpn),
+            MidiMessage::Rpn(rpn) => write!(f, "{:?}", rpn),
+            MidiMessage::ControlChange14(cc) => write!(f, "{:?}", cc),
+            MidiMessage::BankProgramChange(pc) => write!(f, "{:?}", pc),
+            MidiMessage::SongPosition(position) => write!(f, "{:?}", position),
+            MidiMessage::Tempo(bpm) => write!(f, "Tempo: {} BPM", bpm),
+            MidiMessage::Start => write!(f, "Start"),
+            MidiMessage::Stop => write!(f, "Stop"),
+            MidiMessage::Continue => write!(f, "Continue"),
+            MidiMessage::TimingClock => write!(f, "Timing Clock"),
         }
     }
 }
@@ -49,52 +297,264 @@ impl From<&str> for MidiMessage {
         match message {
             "ProgramChange" => MidiMessage::ProgramChange(ProgramChange::default()),
             "ControlChange" => MidiMessage::ControlChange(ControlChange::default()),
+            "NoteOn" => MidiMessage::NoteOn(NoteOn::default()),
+            "NoteOff" => MidiMessage::NoteOff(NoteOff::default()),
+            "SysEx" => MidiMessage::SysEx(SysEx::default()),
+            "Fractal" => MidiMessage::Fractal(FractalMessage::default()),
+            "PitchBend" => MidiMessage::PitchBend(PitchBend::default()),
+            "ChannelPressure" => MidiMessage::ChannelPressure(ChannelPressure::default()),
+            "PolyPressure" => MidiMessage::PolyPressure(PolyPressure::default()),
+            "Nrpn" => MidiMessage::Nrpn(Nrpn::default()),
+            "Rpn" => MidiMessage::Rpn(Rpn::default()),
+            "ControlChange14" => MidiMessage::ControlChange14(ControlChange14::default()),
+            "BankProgramChange" => MidiMessage::BankProgramChange(BankProgramChange::default()),
+            "SongPosition" => MidiMessage::SongPosition(SongPosition::default()),
+            "Tempo" => MidiMessage::Tempo(clock::DEFAULT_BPM),
+            "Start" => MidiMessage::Start,
+            "Stop" => MidiMessage::Stop,
+            "Continue" => MidiMessage::Continue,
             _ => MidiMessage::Empty,
         }
     }
 }
 
-pub fn get_available_ports() -> MidiOutputPorts {
-    let midi_out = MidiOutput::new("My MIDI Output").unwrap();
-    midi_out.ports()
+/// Sends `midi_messages` to `device`, or to their own device where they override it, without
+/// blocking and honouring their delays. `bank_select` gives the bank select mode of a device and
+/// `on_error` is called for every step that fails to send. Use `scheduler::play` to follow the
+/// progress of the sequence.
+pub fn send_midi_messages(
+    device: &DeviceRef,
+    midi_messages: Vec<PresetMessage>,
+    bank_select: impl Fn(&DeviceRef) -> BankSelectMode,
+    on_error: impl Fn(SendFailure) + 'static,
+) {
+    scheduler::play(device, midi_messages, bank_select, |_| (), on_error);
 }
 
-pub fn send_midi_messages(device: usize, midi_messages: Vec<MidiMessage>) {
-    for message in midi_messages {
-        match message {
-            MidiMessage::Empty => (),
-            MidiMessage::Delay(delay) => sleep(std::time::Duration::from_millis(delay)),
-            MidiMessage::ProgramChange(pc) => {
-                match send_midi_message(device, pc.as_u8().as_slice()) {
-                    Ok(_) => log::info!("Program change sent successfully"),
-                    Err(err) => log::error!("Error sending program change: {}", err),
-                }
-            }
-            MidiMessage::ControlChange(cc) => {
-                match send_midi_message(device, cc.as_u8().as_slice()) {
-                    Ok(_) => log::info!("Control change sent successfully"),
-                    Err(err) => log::error!("Error sending program change: {}", err),
+/// Stops playback and the clock, then sends All Notes Off, All Sound Off and Reset All
+/// Controllers on every channel of every output. Returns what went wrong; an output stops at its
+/// first error and the next one is tried.
+pub fn panic() -> Vec<MidiError> {
+    scheduler::cancel();
+    let mut errors = vec![];
+    if let Err(err) = clock::stop() {
+        log::error!("Error stopping clock: {}", err);
+        errors.push(err);
+    }
+    'ports: for port in output_names() {
+        for channel in MidiChannel::all() {
+            for control in [ALL_NOTES_OFF, ALL_SOUND_OFF, RESET_ALL_CONTROLLERS] {
+                let message = ControlChange {
+                    channel,
+                    control_number: U7::from_masked(control),
+                    value: U7::default(),
+                };
+                if let Err(err) = connection::send(&port, message.as_u8().as_slice()) {
+                    log::error!("Error sending panic to {}: {}", port, err);
+                    errors.push(err);
+                    continue 'ports;
                 }
             }
         }
     }
+    errors
 }
 
-fn connect_to_midi_device(device_index: usize) -> Result<MidiOutputConnection, Box<dyn Error>> {
-    let midi_out = MidiOutput::new("My MIDI Output")?;
-    let out_ports = midi_out.ports();
-    if device_index >= out_ports.len() {
-        return Err("Invalid device index".into());
+/// The output port and bytes of a message that is written straight to `device`. Delays, tempo
+/// changes and transport control go through the clock instead and return `None`.
+pub fn port_message(
+    device: &DeviceRef,
+    bank_select: BankSelectMode,
+    message: &MidiMessage,
+) -> Option<Result<(String, Vec<u8>), MidiError>> {
+    match message {
+        MidiMessage::Empty
+        | MidiMessage::Delay(_)
+        | MidiMessage::Tempo(_)
+        | MidiMessage::Start
+        | MidiMessage::Stop
+        | MidiMessage::Continue => None,
+        message => Some(
+            device
+                .port_name()
+                .map(|port| (port, message.as_u8_with(bank_select)))
+                .ok_or_else(|| MidiError::PortNotFound(device.to_string())),
+        ),
     }
-    Ok(midi_out.connect(&out_ports[device_index], "midir-test")?)
 }
 
-fn send_midi_message(
-    device_index: usize,
-    message: &[u8],
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut conn_out = connect_to_midi_device(device_index)?;
-    conn_out.send(message)?;
-    conn_out.close();
-    Ok(())
+/// Sends a single message to `device` right away. Delays are handled by the scheduler and are
+/// ignored here.
+pub fn send_midi_message(
+    device: &DeviceRef,
+    bank_select: BankSelectMode,
+    message: MidiMessage,
+) -> Result<(), MidiError> {
+    let port = device
+        .port_name()
+        .ok_or_else(|| MidiError::PortNotFound(device.to_string()))?;
+    match message {
+        MidiMessage::Empty | MidiMessage::Delay(_) => Ok(()),
+        MidiMessage::Tempo(bpm) => {
+            clock::set_tempo(bpm);
+            Ok(())
+        }
+        MidiMessage::Start => clock::start(&port),
+        MidiMessage::Stop => clock::stop(),
+        MidiMessage::Continue => clock::resume(&port),
+        message => connection::send(&port, &message.as_u8_with(bank_select)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::midi::input;
+    use crate::midi::transport::{set_transport, LoopbackTransport};
+
+    fn channel(number: i32) -> MidiChannel {
+        MidiChannel::from_number(number).unwrap()
+    }
+
+    fn cc(number: i32, control_number: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange(ControlChange {
+            channel: channel(number),
+            control_number: U7::from_masked(control_number),
+            value: U7::from_masked(value),
+        })
+    }
+
+    fn program(number: i32, program: u8) -> MidiMessage {
+        MidiMessage::ProgramChange(ProgramChange {
+            channel: channel(number),
+            program: U7::from_masked(program),
+        })
+    }
+
+    #[test]
+    fn learns_messages_of_the_same_kind() {
+        let step = cc(1, 1, 0);
+        assert_eq!(step.learn(&cc(3, 7, 100)), Some(cc(3, 7, 100)));
+        assert_eq!(step.learn(&program(3, 5)), None);
+        assert_eq!(step.learn(&MidiMessage::TimingClock), None);
+        let note = MidiMessage::NoteOn(NoteOn::default());
+        assert_eq!(note.learn(&cc(3, 7, 100)), None);
+        assert_eq!(MidiMessage::Start.learn(&MidiMessage::Start), None);
+    }
+
+    #[test]
+    fn empty_steps_learn_any_channel_message() {
+        assert_eq!(
+            MidiMessage::Empty.learn(&program(2, 9)),
+            Some(program(2, 9))
+        );
+        assert_eq!(MidiMessage::Empty.learn(&MidiMessage::Stop), None);
+    }
+
+    #[test]
+    fn bank_program_changes_keep_their_bank() {
+        let step = MidiMessage::BankProgramChange(BankProgramChange {
+            channel: channel(1),
+            preset: U14::new(3 * 128 + 9).unwrap(),
+        });
+        assert_eq!(
+            step.learn(&program(4, 20)),
+            Some(MidiMessage::BankProgramChange(BankProgramChange {
+                channel: channel(4),
+                preset: U14::new(3 * 128 + 20).unwrap(),
+            }))
+        );
+        assert_eq!(step.learn(&cc(4, 0, 1)), None);
+    }
+
+    #[test]
+    fn control_changes_14_keep_their_lsb() {
+        let step = MidiMessage::ControlChange14(ControlChange14 {
+            channel: channel(1),
+            control_number: U7::from_masked(1),
+            value: U14::from_parts(0, 5),
+        });
+        assert_eq!(
+            step.learn(&cc(2, 7, 100)),
+            Some(MidiMessage::ControlChange14(ControlChange14 {
+                channel: channel(2),
+                control_number: U7::from_masked(7),
+                value: U14::from_parts(100, 5),
+            }))
+        );
+        // Controllers without an LSB counterpart cannot be learned.
+        assert_eq!(step.learn(&cc(2, 40, 100)), None);
+    }
+
+    #[test]
+    fn send_midi_messages_sends_the_bytes_of_each_step() {
+        let transport = LoopbackTransport::new(&["Synth"]);
+        set_transport(transport.clone());
+        send_midi_messages(
+            &DeviceRef::from("Synth".to_string()),
+            vec![
+                PresetMessage::from(program(1, 5)),
+                PresetMessage::from(MidiMessage::Delay(10)),
+                PresetMessage::from(MidiMessage::Nrpn(Nrpn {
+                    channel: channel(2),
+                    parameter: U14::new(0x0102).unwrap(),
+                    value: U14::new(0x0304).unwrap(),
+                })),
+            ],
+            |_| BankSelectMode::default(),
+            |failure| panic!("{:?}", failure),
+        );
+        assert_eq!(
+            transport.sent_to("Synth"),
+            vec![
+                vec![0xC0, 5],
+                vec![0xB1, 99, 0x02, 0xB1, 98, 0x02, 0xB1, 6, 0x06, 0xB1, 38, 0x04],
+            ]
+        );
+    }
+
+    #[test]
+    fn panic_resets_every_channel_of_every_output() {
+        let transport = LoopbackTransport::new(&["Synth", "Pedal"]);
+        set_transport(transport.clone());
+        assert_eq!(panic(), vec![]);
+        for port in ["Synth", "Pedal"] {
+            let expected: Vec<Vec<u8>> = MidiChannel::all()
+                .flat_map(|channel| {
+                    [ALL_NOTES_OFF, ALL_SOUND_OFF, RESET_ALL_CONTROLLERS]
+                        .map(|control| vec![channel.status(0xB0), control, 0])
+                })
+                .collect();
+            assert_eq!(transport.sent_to(port), expected, "{}", port);
+        }
+    }
+
+    #[test]
+    fn loopback_echoes_to_input_listeners() {
+        let transport = LoopbackTransport::new(&["Synth"]).with_echo();
+        set_transport(transport.clone());
+        let received = Rc::new(RefCell::new(vec![]));
+        let _listener = input::listen({
+            let received = received.clone();
+            move |port, message| {
+                received
+                    .borrow_mut()
+                    .push((port.to_string(), message.clone()))
+            }
+        });
+        connection::send(
+            "Synth",
+            &program(1, 9).as_u8_with(BankSelectMode::default()),
+        )
+        .unwrap();
+        assert_eq!(
+            *received.borrow(),
+            vec![("Synth".to_string(), program(1, 9))]
+        );
+        transport.clear();
+        assert!(transport.sent().is_empty());
+    }
 }
@@ -0,0 +1,179 @@
+//! A log of the MIDI traffic going in and out of the app, shown on the monitor page.
+//!
+//! Every message received on an open input and every message sent to an output is recorded with
+//! the time it was seen. The log keeps the last `MAX_ENTRIES` messages; older ones are dropped.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use crate::midi::clock::now;
+use crate::midi::fractal::FractalMidiCC;
+use crate::midi::midi_message::MidiMessage;
+use crate::midi::note::note_name;
+use crate::midi::parser::MidiParser;
+
+pub const MAX_ENTRIES: usize = 1000;
+
+thread_local! {
+    static ENTRIES: RefCell<VecDeque<MonitorEntry>> = const { RefCell::new(VecDeque::new()) };
+    static PAUSED: Cell<bool> = const { Cell::new(false) };
+    /// Bumped whenever the log changes, so views can tell when to re-render.
+    static VERSION: Cell<u64> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::In => write!(f, "In"),
+            Direction::Out => write!(f, "Out"),
+        }
+    }
+}
+
+/// A message seen on a port, with the time it was seen in milliseconds on the `now()` clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorEntry {
+    pub timestamp: f64,
+    pub direction: Direction,
+    pub port: String,
+    pub message: MidiMessage,
+    pub bytes: Vec<u8>,
+}
+
+impl MonitorEntry {
+    /// The message bytes as space separated hex.
+    pub fn hex(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Records the messages in `bytes`, unless the monitor is paused. Bytes that don't decode to a
+/// message (active sensing, MTC, ...) are left out.
+pub fn record(direction: Direction, port: &str, bytes: &[u8]) {
+    if is_paused() {
+        return;
+    }
+    let timestamp = now();
+    let mut parser = MidiParser::new();
+    let mut start = 0;
+    let mut recorded = vec![];
+    for (i, byte) in bytes.iter().enumerate() {
+        if let Some(message) = parser.push(*byte) {
+            recorded.push(MonitorEntry {
+                timestamp,
+                direction,
+                port: port.to_string(),
+                message,
+                bytes: bytes[start..=i].to_vec(),
+            });
+            start = i + 1;
+        }
+    }
+    if recorded.is_empty() {
+        return;
+    }
+    ENTRIES.with(|entries| {
+        let mut entries = entries.borrow_mut();
+        entries.extend(recorded);
+        while entries.len() > MAX_ENTRIES {
+            entries.pop_front();
+        }
+    });
+    bump();
+}
+
+/// All recorded messages, oldest first.
+pub fn entries() -> Vec<MonitorEntry> {
+    ENTRIES.with(|entries| entries.borrow().iter().cloned().collect())
+}
+
+pub fn clear() {
+    ENTRIES.with(|entries| entries.borrow_mut().clear());
+    bump();
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.with(Cell::get)
+}
+
+pub fn set_paused(paused: bool) {
+    PAUSED.with(|p| p.set(paused));
+    bump();
+}
+
+pub fn version() -> u64 {
+    VERSION.with(Cell::get)
+}
+
+fn bump() {
+    VERSION.with(|version| version.set(version.get() + 1));
+}
+
+/// A readable description of a message. Control changes are labelled with the Fractal function
+/// they are assigned to by default and notes with their pitch.
+pub fn describe(message: &MidiMessage) -> String {
+    match message {
+        MidiMessage::ControlChange(cc) => {
+            let number = cc.control_number.get();
+            match FractalMidiCC::from_number(number) {
+                Some(function) => format!("CC {} ({}) = {}", number, function, cc.value),
+                None => format!("CC {} = {}", number, cc.value),
+            }
+        }
+        MidiMessage::NoteOn(note) => format!(
+            "{} ({}) velocity {}",
+            note_name(note.note.get()),
+            note.note,
+            note.velocity
+        ),
+        MidiMessage::NoteOff(note) => format!(
+            "{} ({}) velocity {}",
+            note_name(note.note.get()),
+            note.note,
+            note.velocity
+        ),
+        MidiMessage::ProgramChange(pc) => format!("Program {}", pc.program),
+        MidiMessage::PitchBend(pitch_bend) => format!("{}", pitch_bend.value),
+        MidiMessage::ChannelPressure(pressure) => format!("{}", pressure.pressure),
+        MidiMessage::PolyPressure(pressure) => format!(
+            "{} ({}) pressure {}",
+            note_name(pressure.note.get()),
+            pressure.note,
+            pressure.pressure
+        ),
+        message => message.to_string(),
+    }
+}
+
+/// The log as tab separated text, one message per line, for saving to a file.
+pub fn export() -> String {
+    let mut text = String::from("Time (ms)\tDirection\tPort\tType\tChannel\tMessage\tBytes\n");
+    for entry in entries() {
+        text.push_str(&format!(
+            "{:.3}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            entry.timestamp,
+            entry.direction,
+            entry.port,
+            entry.message.kind(),
+            entry
+                .message
+                .channel()
+                .map(|channel| channel.to_string())
+                .unwrap_or_default(),
+            describe(&entry.message),
+            entry.hex()
+        ));
+    }
+    text
+}
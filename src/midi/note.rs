@@ -0,0 +1,84 @@
+use crate::midi::channel::MidiChannel;
+use crate::midi::program_change::AsU8;
+use crate::midi::value::U7;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Scientific pitch name of a note number, e.g. 60 is `C4`.
+pub fn note_name(note: u8) -> String {
+    format!(
+        "{}{}",
+        NOTE_NAMES[(note % 12) as usize],
+        (note / 12) as i32 - 1
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteOn {
+    pub channel: MidiChannel,
+    pub note: U7,
+    pub velocity: U7,
+}
+
+impl Default for NoteOn {
+    fn default() -> Self {
+        NoteOn {
+            channel: MidiChannel::default(),
+            note: U7::from_masked(60),
+            velocity: U7::from_masked(100),
+        }
+    }
+}
+
+impl Display for NoteOn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Note On: {:?}", self)
+    }
+}
+
+impl AsU8 for NoteOn {
+    fn as_u8(&self) -> Vec<u8> {
+        vec![
+            self.channel.status(0x90),
+            self.note.get(),
+            self.velocity.get(),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteOff {
+    pub channel: MidiChannel,
+    pub note: U7,
+    pub velocity: U7,
+}
+
+impl Default for NoteOff {
+    fn default() -> Self {
+        NoteOff {
+            channel: MidiChannel::default(),
+            note: U7::from_masked(60),
+            velocity: U7::default(),
+        }
+    }
+}
+
+impl Display for NoteOff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Note Off: {:?}", self)
+    }
+}
+
+impl AsU8 for NoteOff {
+    fn as_u8(&self) -> Vec<u8> {
+        vec![
+            self.channel.status(0x80),
+            self.note.get(),
+            self.velocity.get(),
+        ]
+    }
+}
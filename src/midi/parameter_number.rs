@@ -0,0 +1,277 @@
+use crate::midi::channel::MidiChannel;
+use crate::midi::program_change::AsU8;
+use crate::midi::value::{MidiValueError, U14, U7};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::{Display, Formatter};
+
+const NRPN_MSB: u8 = 99;
+const NRPN_LSB: u8 = 98;
+const RPN_MSB: u8 = 101;
+const RPN_LSB: u8 = 100;
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+
+/// Offset between the MSB and LSB controller of a 14-bit control change. Only CC 0–31 have an
+/// LSB counterpart.
+const LSB_OFFSET: u8 = 32;
+
+/// A Non-Registered Parameter Number change, sent as CC 99/98 followed by data entry CC 6/38.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Nrpn {
+    pub channel: MidiChannel,
+    pub parameter: U14,
+    pub value: U14,
+}
+
+impl Display for Nrpn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NRPN: {:?}", self)
+    }
+}
+
+impl AsU8 for Nrpn {
+    fn as_u8(&self) -> Vec<u8> {
+        parameter_change(
+            self.channel,
+            (NRPN_MSB, NRPN_LSB),
+            self.parameter,
+            self.value,
+        )
+    }
+}
+
+/// A Registered Parameter Number change, sent as CC 101/100 followed by data entry CC 6/38.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Rpn {
+    pub channel: MidiChannel,
+    pub parameter: U14,
+    pub value: U14,
+}
+
+impl Display for Rpn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RPN: {:?}", self)
+    }
+}
+
+impl AsU8 for Rpn {
+    fn as_u8(&self) -> Vec<u8> {
+        parameter_change(self.channel, (RPN_MSB, RPN_LSB), self.parameter, self.value)
+    }
+}
+
+/// A high resolution control change, sent as the MSB on CC n followed by the LSB on CC n + 32.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlChange14 {
+    pub channel: MidiChannel,
+    /// The MSB controller, 0–31.
+    #[serde(deserialize_with = "deserialize_control_number")]
+    pub control_number: U7,
+    pub value: U14,
+}
+
+impl ControlChange14 {
+    pub const MAX_CONTROL_NUMBER: u8 = LSB_OFFSET - 1;
+
+    /// Fails if `control_number` has no LSB counterpart.
+    pub fn new(
+        channel: MidiChannel,
+        control_number: U7,
+        value: U14,
+    ) -> Result<ControlChange14, MidiValueError> {
+        if control_number.get() > ControlChange14::MAX_CONTROL_NUMBER {
+            return Err(MidiValueError::DataOutOfRange {
+                value: control_number.get() as i32,
+                max: ControlChange14::MAX_CONTROL_NUMBER as u16,
+            });
+        }
+        Ok(ControlChange14 {
+            channel,
+            control_number,
+            value,
+        })
+    }
+}
+
+/// Clamps stored control numbers to 0–31, like `U7` clamps its values.
+fn deserialize_control_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U7, D::Error> {
+    let control_number = U7::deserialize(deserializer)?;
+    Ok(U7::from_masked(
+        control_number
+            .get()
+            .min(ControlChange14::MAX_CONTROL_NUMBER),
+    ))
+}
+
+impl Default for ControlChange14 {
+    fn default() -> Self {
+        ControlChange14 {
+            channel: MidiChannel::default(),
+            control_number: U7::from_masked(1),
+            value: U14::default(),
+        }
+    }
+}
+
+impl Display for ControlChange14 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CC 14-bit: {:?}", self)
+    }
+}
+
+impl AsU8 for ControlChange14 {
+    fn as_u8(&self) -> Vec<u8> {
+        let status = self.channel.status(0xB0);
+        let control_number = self.control_number.get();
+        vec![
+            status,
+            control_number,
+            self.value.msb(),
+            status,
+            control_number + LSB_OFFSET,
+            self.value.lsb(),
+        ]
+    }
+}
+
+fn parameter_change(
+    channel: MidiChannel,
+    selectors: (u8, u8),
+    parameter: U14,
+    value: U14,
+) -> Vec<u8> {
+    let status = channel.status(0xB0);
+    vec![
+        status,
+        selectors.0,
+        parameter.msb(),
+        status,
+        selectors.1,
+        parameter.lsb(),
+        status,
+        DATA_ENTRY_MSB,
+        value.msb(),
+        status,
+        DATA_ENTRY_LSB,
+        value.lsb(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::midi_message::MidiMessage;
+    use crate::midi::parser::MidiParser;
+
+    /// Parses `bytes` back and returns the control changes as (channel, controller, value).
+    fn control_changes(bytes: &[u8]) -> Vec<(MidiChannel, u8, u8)> {
+        MidiParser::parse(bytes)
+            .into_iter()
+            .map(|message| match message {
+                MidiMessage::ControlChange(cc) => {
+                    (cc.channel, cc.control_number.get(), cc.value.get())
+                }
+                other => panic!("expected a control change, got {:?}", other),
+            })
+            .collect()
+    }
+
+    /// Reassembles a parameter change from the four control changes it is sent as.
+    fn parameter_change(bytes: &[u8]) -> (MidiChannel, (u8, u8), U14, U14) {
+        let ccs = control_changes(bytes);
+        assert_eq!(ccs.len(), 4);
+        assert!(ccs.iter().all(|(channel, _, _)| *channel == ccs[0].0));
+        assert_eq!((ccs[2].1, ccs[3].1), (DATA_ENTRY_MSB, DATA_ENTRY_LSB));
+        (
+            ccs[0].0,
+            (ccs[0].1, ccs[1].1),
+            U14::from_parts(ccs[0].2, ccs[1].2),
+            U14::from_parts(ccs[2].2, ccs[3].2),
+        )
+    }
+
+    fn channels() -> impl Iterator<Item = MidiChannel> {
+        [1, 10, 16]
+            .into_iter()
+            .map(|number| MidiChannel::from_number(number).unwrap())
+    }
+
+    fn values() -> impl Iterator<Item = U14> + Clone {
+        [0, 1, 0x7F, 0x80, 0x2000, U14::MAX]
+            .into_iter()
+            .map(|value| U14::new(value).unwrap())
+    }
+
+    #[test]
+    fn nrpn_round_trips() {
+        for channel in channels() {
+            for parameter in values() {
+                for value in values() {
+                    let nrpn = Nrpn {
+                        channel,
+                        parameter,
+                        value,
+                    };
+                    assert_eq!(
+                        parameter_change(&nrpn.as_u8()),
+                        (channel, (NRPN_MSB, NRPN_LSB), parameter, value)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rpn_round_trips() {
+        for channel in channels() {
+            for parameter in values() {
+                for value in values() {
+                    let rpn = Rpn {
+                        channel,
+                        parameter,
+                        value,
+                    };
+                    assert_eq!(
+                        parameter_change(&rpn.as_u8()),
+                        (channel, (RPN_MSB, RPN_LSB), parameter, value)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn control_change_14_rejects_numbers_without_lsb() {
+        let channel = MidiChannel::default();
+        let value = U14::CENTER;
+        assert!(ControlChange14::new(channel, U7::new(31).unwrap(), value).is_ok());
+        assert_eq!(
+            ControlChange14::new(channel, U7::new(40).unwrap(), value),
+            Err(MidiValueError::DataOutOfRange { value: 40, max: 31 })
+        );
+    }
+
+    #[test]
+    fn control_change_14_round_trips() {
+        for channel in channels() {
+            for control_number in [0, 1, 7, 31] {
+                for value in values() {
+                    let cc = ControlChange14 {
+                        channel,
+                        control_number: U7::new(control_number).unwrap(),
+                        value,
+                    };
+                    let ccs = control_changes(&cc.as_u8());
+                    assert_eq!(
+                        ccs,
+                        vec![
+                            (channel, control_number, value.msb()),
+                            (channel, control_number + LSB_OFFSET, value.lsb()),
+                        ]
+                    );
+                    assert_eq!(U14::from_parts(ccs[0].2, ccs[1].2), value);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use crate::midi::channel::MidiChannel;
+use crate::midi::clock::{SongPosition, CONTINUE, SONG_POSITION, START, STOP, TIMING_CLOCK};
+use crate::midi::control_change::ControlChange;
+use crate::midi::fractal::sysex::FractalMessage;
+use crate::midi::midi_message::MidiMessage;
+use crate::midi::note::{NoteOff, NoteOn};
+use crate::midi::pitch_bend::PitchBend;
+use crate::midi::pressure::{ChannelPressure, PolyPressure};
+use crate::midi::program_change::ProgramChange;
+use crate::midi::sysex::{SysEx, SYSEX_END, SYSEX_START};
+use crate::midi::value::{U14, U7};
+
+/// Streaming decoder that turns raw MIDI bytes back into `MidiMessage`s.
+///
+/// Handles running status, realtime bytes interleaved anywhere in the stream (including inside
+/// SysEx) and SysEx of any length. Composite messages such as NRPN or bank select come back as
+/// the individual control and program changes they were sent as. Bytes without a `MidiMessage`
+/// counterpart (active sensing, MTC, song select, ...) are skipped.
+#[derive(Debug, Default)]
+pub struct MidiParser {
+    status: Option<u8>,
+    data: Vec<u8>,
+    sysex: Option<Vec<u8>>,
+}
+
+impl MidiParser {
+    pub fn new() -> MidiParser {
+        MidiParser::default()
+    }
+
+    /// Decodes a complete byte buffer.
+    pub fn parse(bytes: &[u8]) -> Vec<MidiMessage> {
+        MidiParser::new().feed(bytes)
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<MidiMessage> {
+        bytes.iter().filter_map(|b| self.push(*b)).collect()
+    }
+
+    /// Feeds a single byte, returning a message once one is complete.
+    pub fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        if byte >= TIMING_CLOCK {
+            return realtime(byte);
+        }
+        match byte {
+            SYSEX_START => {
+                self.status = None;
+                self.sysex = Some(vec![SYSEX_START]);
+                None
+            }
+            SYSEX_END => {
+                let mut data = self.sysex.take()?;
+                data.push(SYSEX_END);
+                sysex(data)
+            }
+            0x80..=0xEF => {
+                self.sysex = None;
+                self.status = Some(byte);
+                self.data.clear();
+                None
+            }
+            0xF1..=0xF6 => {
+                self.sysex = None;
+                self.data.clear();
+                // Undefined statuses and tune request have no data bytes and no message
+                // counterpart.
+                self.status = Some(byte).filter(|status| data_length(*status) > 0);
+                None
+            }
+            _ => {
+                if let Some(sysex) = self.sysex.as_mut() {
+                    sysex.push(byte);
+                    return None;
+                }
+                let status = self.status?;
+                self.data.push(byte);
+                if self.data.len() < data_length(status) {
+                    return None;
+                }
+                let message = decode(status, &self.data);
+                self.data.clear();
+                // Running status only applies to channel messages.
+                if status >= 0xF0 {
+                    self.status = None;
+                }
+                message
+            }
+        }
+    }
+}
+
+/// Number of data bytes following a channel or system common status byte. SysEx has no fixed
+/// length and counts as none.
+pub fn data_length(status: u8) -> usize {
+    match status & 0xF0 {
+        0xC0 | 0xD0 => 1,
+        0xF0 => match status {
+            SONG_POSITION => 2,
+            0xF1 | 0xF3 => 1,
+            _ => 0,
+        },
+        _ => 2,
+    }
+}
+
+fn decode(status: u8, data: &[u8]) -> Option<MidiMessage> {
+    let channel = MidiChannel::from_status(status);
+    let message = match status & 0xF0 {
+        0x80 => MidiMessage::NoteOff(NoteOff {
+            channel,
+            note: U7::from_masked(data[0]),
+            velocity: U7::from_masked(data[1]),
+        }),
+        0x90 => MidiMessage::NoteOn(NoteOn {
+            channel,
+            note: U7::from_masked(data[0]),
+            velocity: U7::from_masked(data[1]),
+        }),
+        0xA0 => MidiMessage::PolyPressure(PolyPressure {
+            channel,
+            note: U7::from_masked(data[0]),
+            pressure: U7::from_masked(data[1]),
+        }),
+        0xB0 => MidiMessage::ControlChange(ControlChange {
+            channel,
+            control_number: U7::from_masked(data[0]),
+            value: U7::from_masked(data[1]),
+        }),
+        0xC0 => MidiMessage::ProgramChange(ProgramChange {
+            channel,
+            program: U7::from_masked(data[0]),
+        }),
+        0xD0 => MidiMessage::ChannelPressure(ChannelPressure {
+            channel,
+            pressure: U7::from_masked(data[0]),
+        }),
+        0xE0 => MidiMessage::PitchBend(PitchBend {
+            channel,
+            value: U14::from_parts(data[1], data[0]),
+        }),
+        _ if status == SONG_POSITION => MidiMessage::SongPosition(SongPosition {
+            position: U14::from_parts(data[1], data[0]),
+        }),
+        _ => return None,
+    };
+    Some(message)
+}
+
+fn realtime(byte: u8) -> Option<MidiMessage> {
+    match byte {
+        TIMING_CLOCK => Some(MidiMessage::TimingClock),
+        START => Some(MidiMessage::Start),
+        CONTINUE => Some(MidiMessage::Continue),
+        STOP => Some(MidiMessage::Stop),
+        _ => None,
+    }
+}
+
+fn sysex(data: Vec<u8>) -> Option<MidiMessage> {
+    if let Ok(fractal) = FractalMessage::parse(&data) {
+        return Some(MidiMessage::Fractal(fractal));
+    }
+    SysEx::new(data).ok().map(MidiMessage::SysEx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::bank_select::{BankProgramChange, BankSelectMode};
+    use crate::midi::fractal::sysex::{FractalCommand, FractalModel};
+    use crate::midi::parameter_number::{ControlChange14, Nrpn, Rpn};
+
+    fn channel() -> MidiChannel {
+        MidiChannel::from_number(5).unwrap()
+    }
+
+    fn u7(value: u8) -> U7 {
+        U7::new(value).unwrap()
+    }
+
+    fn u14(value: u16) -> U14 {
+        U14::new(value).unwrap()
+    }
+
+    fn cc(control_number: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange(ControlChange {
+            channel: channel(),
+            control_number: u7(control_number),
+            value: u7(value),
+        })
+    }
+
+    fn note_on(note: u8) -> MidiMessage {
+        MidiMessage::NoteOn(NoteOn {
+            channel: channel(),
+            note: u7(note),
+            velocity: u7(100),
+        })
+    }
+
+    /// One message of every kind, with values that tell the data bytes apart.
+    fn samples() -> Vec<MidiMessage> {
+        vec![
+            MidiMessage::Delay(250),
+            MidiMessage::Empty,
+            MidiMessage::ProgramChange(ProgramChange {
+                channel: channel(),
+                program: u7(42),
+            }),
+            cc(7, 99),
+            note_on(60),
+            MidiMessage::NoteOff(NoteOff {
+                channel: channel(),
+                note: u7(61),
+                velocity: u7(12),
+            }),
+            MidiMessage::SysEx(SysEx::new(vec![SYSEX_START, 0x7D, 0x01, 0x02, SYSEX_END]).unwrap()),
+            MidiMessage::Fractal(FractalMessage {
+                model: FractalModel::FM3,
+                command: FractalCommand::Scene(3),
+            }),
+            MidiMessage::PitchBend(PitchBend {
+                channel: channel(),
+                value: u14(0x1234),
+            }),
+            MidiMessage::ChannelPressure(ChannelPressure {
+                channel: channel(),
+                pressure: u7(33),
+            }),
+            MidiMessage::PolyPressure(PolyPressure {
+                channel: channel(),
+                note: u7(64),
+                pressure: u7(34),
+            }),
+            MidiMessage::Nrpn(Nrpn {
+                channel: channel(),
+                parameter: u14(0x0102),
+                value: u14(0x0304),
+            }),
+            MidiMessage::Rpn(Rpn {
+                channel: channel(),
+                parameter: u14(0x0506),
+                value: u14(0x0708),
+            }),
+            MidiMessage::ControlChange14(ControlChange14 {
+                channel: channel(),
+                control_number: u7(2),
+                value: u14(0x0A0B),
+            }),
+            MidiMessage::BankProgramChange(BankProgramChange {
+                channel: channel(),
+                preset: u14(3 * 128 + 9),
+            }),
+            MidiMessage::SongPosition(SongPosition {
+                position: u14(0x0C0D),
+            }),
+            MidiMessage::Tempo(140),
+            MidiMessage::Start,
+            MidiMessage::Stop,
+            MidiMessage::Continue,
+            MidiMessage::TimingClock,
+        ]
+    }
+
+    /// What parsing the bytes of `message` gives back: the message itself, the messages a
+    /// composite message is sent as, or nothing for entries without bytes.
+    fn parsed(message: &MidiMessage) -> Vec<MidiMessage> {
+        match message {
+            MidiMessage::Delay(_) | MidiMessage::Empty | MidiMessage::Tempo(_) => vec![],
+            MidiMessage::Nrpn(_) => vec![cc(99, 0x02), cc(98, 0x02), cc(6, 0x06), cc(38, 0x04)],
+            MidiMessage::Rpn(_) => vec![cc(101, 0x0A), cc(100, 0x06), cc(6, 0x0E), cc(38, 0x08)],
+            MidiMessage::ControlChange14(_) => vec![cc(2, 0x14), cc(34, 0x0B)],
+            MidiMessage::BankProgramChange(_) => vec![
+                cc(0, 3),
+                MidiMessage::ProgramChange(ProgramChange {
+                    channel: channel(),
+                    program: u7(9),
+                }),
+            ],
+            MidiMessage::ProgramChange(_)
+            | MidiMessage::ControlChange(_)
+            | MidiMessage::NoteOn(_)
+            | MidiMessage::NoteOff(_)
+            | MidiMessage::SysEx(_)
+            | MidiMessage::Fractal(_)
+            | MidiMessage::PitchBend(_)
+            | MidiMessage::ChannelPressure(_)
+            | MidiMessage::PolyPressure(_)
+            | MidiMessage::SongPosition(_)
+            | MidiMessage::Start
+            | MidiMessage::Stop
+            | MidiMessage::Continue
+            | MidiMessage::TimingClock => vec![message.clone()],
+        }
+    }
+
+    #[test]
+    fn parses_what_every_message_sends() {
+        for message in samples() {
+            let bytes = message.as_u8_with(BankSelectMode::Msb);
+            assert_eq!(MidiParser::parse(&bytes), parsed(&message), "{}", message);
+        }
+    }
+
+    #[test]
+    fn parses_messages_back_to_back() {
+        let messages = samples();
+        let bytes: Vec<u8> = messages
+            .iter()
+            .flat_map(|message| message.as_u8_with(BankSelectMode::Msb))
+            .collect();
+        let expected: Vec<MidiMessage> = messages.iter().flat_map(parsed).collect();
+        assert_eq!(MidiParser::parse(&bytes), expected);
+    }
+
+    #[test]
+    fn applies_running_status() {
+        assert_eq!(
+            MidiParser::parse(&[0x94, 60, 100, 62, 100, 64, 100]),
+            vec![note_on(60), note_on(62), note_on(64)]
+        );
+        assert_eq!(
+            MidiParser::parse(&[0xB4, 99, 0x02, 98, 0x02, 6, 0x06, 38, 0x04]),
+            vec![cc(99, 0x02), cc(98, 0x02), cc(6, 0x06), cc(38, 0x04)]
+        );
+        let program = |program| {
+            MidiMessage::ProgramChange(ProgramChange {
+                channel: channel(),
+                program: u7(program),
+            })
+        };
+        assert_eq!(
+            MidiParser::parse(&[0xC4, 1, 2, 3]),
+            vec![program(1), program(2), program(3)]
+        );
+    }
+
+    #[test]
+    fn system_messages_cancel_running_status() {
+        // Song position, an undefined status and tune request all end running status.
+        for system in [&[0xF2, 0x01, 0x02][..], &[0xF4], &[0xF5], &[0xF6]] {
+            let mut bytes = vec![0x94, 60, 100];
+            bytes.extend(system);
+            bytes.extend([62, 100]);
+            let messages = MidiParser::parse(&bytes);
+            assert_eq!(messages.first(), Some(&note_on(60)), "{:02X?}", system);
+            assert!(!messages.contains(&note_on(62)), "{:02X?}", system);
+        }
+    }
+
+    #[test]
+    fn undefined_statuses_have_no_data() {
+        assert_eq!(data_length(0xF4), 0);
+        assert_eq!(data_length(0xF5), 0);
+        assert_eq!(data_length(0xF6), 0);
+        assert_eq!(data_length(0xF1), 1);
+        assert_eq!(data_length(0xF3), 1);
+        assert_eq!(data_length(SONG_POSITION), 2);
+        // The data byte after an undefined status is not taken as its data.
+        assert_eq!(
+            MidiParser::parse(&[0xF4, 0xC4, 7]),
+            vec![MidiMessage::ProgramChange(ProgramChange {
+                channel: channel(),
+                program: u7(7),
+            })]
+        );
+    }
+
+    #[test]
+    fn realtime_bytes_interleave_anywhere() {
+        assert_eq!(
+            MidiParser::parse(&[0x94, TIMING_CLOCK, 60, START, 100, 62, STOP, 100]),
+            vec![
+                MidiMessage::TimingClock,
+                MidiMessage::Start,
+                note_on(60),
+                MidiMessage::Stop,
+                note_on(62),
+            ]
+        );
+        assert_eq!(
+            MidiParser::parse(&[0xE4, 0x34, CONTINUE, 0x24]),
+            vec![
+                MidiMessage::Continue,
+                MidiMessage::PitchBend(PitchBend {
+                    channel: channel(),
+                    value: u14(0x1234),
+                }),
+            ]
+        );
+        // Active sensing has no message counterpart and is dropped, without breaking the SysEx.
+        assert_eq!(
+            MidiParser::parse(&[SYSEX_START, 0x7D, TIMING_CLOCK, 0x01, 0xFE, SYSEX_END]),
+            vec![
+                MidiMessage::TimingClock,
+                MidiMessage::SysEx(SysEx::new(vec![SYSEX_START, 0x7D, 0x01, SYSEX_END]).unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_state_across_feeds() {
+        let mut parser = MidiParser::new();
+        assert_eq!(parser.feed(&[0x94, 60]), vec![]);
+        assert_eq!(parser.feed(&[100, 62]), vec![note_on(60)]);
+        assert_eq!(parser.feed(&[100]), vec![note_on(62)]);
+        assert_eq!(parser.feed(&[SYSEX_START, 0x7D]), vec![]);
+        assert_eq!(
+            parser.feed(&[0x02, SYSEX_END]),
+            vec![MidiMessage::SysEx(
+                SysEx::new(vec![SYSEX_START, 0x7D, 0x02, SYSEX_END]).unwrap()
+            )]
+        );
+    }
+
+    #[test]
+    fn a_status_byte_aborts_unfinished_sysex() {
+        assert_eq!(
+            MidiParser::parse(&[SYSEX_START, 0x7D, 0x01, 0x94, 60, 100, SYSEX_END]),
+            vec![note_on(60)]
+        );
+    }
+}
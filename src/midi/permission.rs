@@ -0,0 +1,41 @@
+//! Permission to use MIDI. Browsers grant it with or without SysEx, or not at all; native
+//! backends need no permission and can always send SysEx.
+
+use crate::midi::error::MidiError;
+#[cfg(target_arch = "wasm32")]
+use crate::midi::web_midi;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))] // Only browsers refuse or defer access.
+pub enum Permission {
+    #[default]
+    NotRequested,
+    Pending,
+    Granted {
+        sysex: bool,
+    },
+    /// MIDI access was refused altogether, for the reason given.
+    Denied(String),
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn permission() -> Permission {
+    web_midi::permission()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn permission() -> Permission {
+    Permission::Granted { sysex: true }
+}
+
+/// Requests MIDI access, including SysEx if `sysex` is set. Returns `MidiError::SysExDenied` if
+/// access was granted without SysEx.
+#[cfg(target_arch = "wasm32")]
+pub async fn request(sysex: bool) -> Result<(), MidiError> {
+    web_midi::request(sysex).await
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn request(_sysex: bool) -> Result<(), MidiError> {
+    Ok(())
+}
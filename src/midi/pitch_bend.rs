@@ -0,0 +1,68 @@
+use crate::midi::channel::MidiChannel;
+use crate::midi::program_change::AsU8;
+use crate::midi::value::U14;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PitchBend {
+    pub channel: MidiChannel,
+    pub value: U14,
+}
+
+impl Default for PitchBend {
+    fn default() -> Self {
+        PitchBend {
+            channel: MidiChannel::default(),
+            value: U14::CENTER,
+        }
+    }
+}
+
+impl Display for PitchBend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Pitch Bend: {:?}", self)
+    }
+}
+
+impl AsU8 for PitchBend {
+    fn as_u8(&self) -> Vec<u8> {
+        vec![
+            self.channel.status(0xE0),
+            self.value.lsb(),
+            self.value.msb(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::midi_message::MidiMessage;
+    use crate::midi::parser::MidiParser;
+
+    #[test]
+    fn round_trips() {
+        for channel in [1, 10, 16] {
+            for value in [0, 1, 0x7F, 0x80, 0x2000, U14::MAX] {
+                let bend = PitchBend {
+                    channel: MidiChannel::from_number(channel).unwrap(),
+                    value: U14::new(value).unwrap(),
+                };
+                assert_eq!(
+                    MidiParser::parse(&bend.as_u8()),
+                    vec![MidiMessage::PitchBend(bend)]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sends_lsb_first() {
+        let bend = PitchBend {
+            channel: MidiChannel::from_number(3).unwrap(),
+            value: U14::new(0x2001).unwrap(),
+        };
+        assert_eq!(bend.as_u8(), vec![0xE2, 0x01, 0x40]);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::midi::channel::MidiChannel;
+use crate::midi::program_change::AsU8;
+use crate::midi::value::U7;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ChannelPressure {
+    pub channel: MidiChannel,
+    pub pressure: U7,
+}
+
+impl Display for ChannelPressure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Channel Pressure: {:?}", self)
+    }
+}
+
+impl AsU8 for ChannelPressure {
+    fn as_u8(&self) -> Vec<u8> {
+        vec![self.channel.status(0xD0), self.pressure.get()]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolyPressure {
+    pub channel: MidiChannel,
+    pub note: U7,
+    pub pressure: U7,
+}
+
+impl Default for PolyPressure {
+    fn default() -> Self {
+        PolyPressure {
+            channel: MidiChannel::default(),
+            note: U7::from_masked(60),
+            pressure: U7::default(),
+        }
+    }
+}
+
+impl Display for PolyPressure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Poly Pressure: {:?}", self)
+    }
+}
+
+impl AsU8 for PolyPressure {
+    fn as_u8(&self) -> Vec<u8> {
+        vec![
+            self.channel.status(0xA0),
+            self.note.get(),
+            self.pressure.get(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::midi_message::MidiMessage;
+    use crate::midi::parser::MidiParser;
+
+    #[test]
+    fn channel_pressure_round_trips() {
+        for channel in [1, 10, 16] {
+            for pressure in [0, 1, 64, U7::MAX] {
+                let message = ChannelPressure {
+                    channel: MidiChannel::from_number(channel).unwrap(),
+                    pressure: U7::new(pressure).unwrap(),
+                };
+                assert_eq!(
+                    MidiParser::parse(&message.as_u8()),
+                    vec![MidiMessage::ChannelPressure(message)]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn poly_pressure_round_trips() {
+        for channel in [1, 10, 16] {
+            for note in [0, 60, U7::MAX] {
+                for pressure in [0, 1, 64, U7::MAX] {
+                    let message = PolyPressure {
+                        channel: MidiChannel::from_number(channel).unwrap(),
+                        note: U7::new(note).unwrap(),
+                        pressure: U7::new(pressure).unwrap(),
+                    };
+                    assert_eq!(
+                        MidiParser::parse(&message.as_u8()),
+                        vec![MidiMessage::PolyPressure(message)]
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,23 +1,15 @@
 use crate::midi::channel::MidiChannel;
+use crate::midi::value::U7;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
 pub trait AsU8 {
     fn as_u8(&self) -> Vec<u8>;
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ProgramChange {
-    pub channel: u8,
-    pub program: u8,
-}
-
-impl Default for ProgramChange {
-    fn default() -> Self {
-        ProgramChange {
-            channel: 1,
-            program: 0,
-        }
-    }
+    pub channel: MidiChannel,
+    pub program: U7,
 }
 
 impl Display for ProgramChange {
@@ -28,6 +20,6 @@ impl Display for ProgramChange {
 
 impl AsU8 for ProgramChange {
     fn as_u8(&self) -> Vec<u8> {
-        vec![0xC0 + self.channel as u8, self.program]
+        vec![self.channel.status(0xC0), self.program.get()]
     }
 }
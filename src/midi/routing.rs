@@ -0,0 +1,408 @@
+//! MIDI thru: forwarding incoming messages from inputs to outputs.
+//!
+//! Each `ThruRoute` forwards what arrives on one input to one output, optionally keeping only some
+//! message types or one channel, and rewriting the channel, controller numbers and controller
+//! values on the way. The active routes are kept here rather than read from storage for every
+//! message, since thru traffic such as clock can be dense.
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use crate::midi::bank_select::BankSelectMode;
+use crate::midi::channel::MidiChannel;
+use crate::midi::connection;
+use crate::midi::control_change::ControlChange;
+use crate::midi::midi_message::MidiMessage;
+use crate::midi::value::U7;
+
+thread_local! {
+    static ROUTES: RefCell<Vec<ThruRoute>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The kinds of message that can arrive on an input. Routes store them under the names
+/// `MidiMessage::kind` gives them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence, Serialize, Deserialize)]
+pub enum RouteKind {
+    #[serde(rename = "Program Change")]
+    ProgramChange,
+    #[serde(rename = "Control Change")]
+    ControlChange,
+    #[serde(rename = "Note On")]
+    NoteOn,
+    #[serde(rename = "Note Off")]
+    NoteOff,
+    #[serde(rename = "Pitch Bend")]
+    PitchBend,
+    #[serde(rename = "Channel Pressure")]
+    ChannelPressure,
+    #[serde(rename = "Poly Pressure")]
+    PolyPressure,
+    SysEx,
+    #[serde(rename = "Fractal SysEx")]
+    Fractal,
+    #[serde(rename = "Song Position")]
+    SongPosition,
+    Start,
+    Stop,
+    Continue,
+    #[serde(rename = "Timing Clock")]
+    TimingClock,
+}
+
+impl RouteKind {
+    /// The kind of `message`, or `None` for messages that never arrive on an input.
+    pub fn of(message: &MidiMessage) -> Option<RouteKind> {
+        let kind = match message {
+            MidiMessage::ProgramChange(_) => RouteKind::ProgramChange,
+            MidiMessage::ControlChange(_) => RouteKind::ControlChange,
+            MidiMessage::NoteOn(_) => RouteKind::NoteOn,
+            MidiMessage::NoteOff(_) => RouteKind::NoteOff,
+            MidiMessage::PitchBend(_) => RouteKind::PitchBend,
+            MidiMessage::ChannelPressure(_) => RouteKind::ChannelPressure,
+            MidiMessage::PolyPressure(_) => RouteKind::PolyPressure,
+            MidiMessage::SysEx(_) => RouteKind::SysEx,
+            MidiMessage::Fractal(_) => RouteKind::Fractal,
+            MidiMessage::SongPosition(_) => RouteKind::SongPosition,
+            MidiMessage::Start => RouteKind::Start,
+            MidiMessage::Stop => RouteKind::Stop,
+            MidiMessage::Continue => RouteKind::Continue,
+            MidiMessage::TimingClock => RouteKind::TimingClock,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+impl Display for RouteKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteKind::ProgramChange => write!(f, "Program Change"),
+            RouteKind::ControlChange => write!(f, "Control Change"),
+            RouteKind::NoteOn => write!(f, "Note On"),
+            RouteKind::NoteOff => write!(f, "Note Off"),
+            RouteKind::PitchBend => write!(f, "Pitch Bend"),
+            RouteKind::ChannelPressure => write!(f, "Channel Pressure"),
+            RouteKind::PolyPressure => write!(f, "Poly Pressure"),
+            RouteKind::SysEx => write!(f, "SysEx"),
+            RouteKind::Fractal => write!(f, "Fractal SysEx"),
+            RouteKind::SongPosition => write!(f, "Song Position"),
+            RouteKind::Start => write!(f, "Start"),
+            RouteKind::Stop => write!(f, "Stop"),
+            RouteKind::Continue => write!(f, "Continue"),
+            RouteKind::TimingClock => write!(f, "Timing Clock"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ThruRoute {
+    pub input: String,
+    pub output: String,
+    /// Kinds of message to forward; all of them when empty.
+    #[serde(default)]
+    pub kinds: Vec<RouteKind>,
+    /// Only forward channel messages on this channel. Messages without a channel, such as SysEx,
+    /// clock and song position, always pass; leave them out of `kinds` to drop them.
+    #[serde(default)]
+    pub channel: Option<MidiChannel>,
+    /// Channel to move channel messages to.
+    #[serde(default)]
+    pub remap_channel: Option<MidiChannel>,
+    #[serde(default)]
+    pub cc_remap: Vec<CcRemap>,
+    #[serde(default)]
+    pub scale: ValueScale,
+}
+
+/// Sends controller `from` out as controller `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct CcRemap {
+    pub from: U7,
+    pub to: U7,
+}
+
+/// Maps controller values 0-127 linearly onto `min`-`max`. Setting `min` above `max` inverts the
+/// controller.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ValueScale {
+    pub min: U7,
+    pub max: U7,
+}
+
+impl Default for ValueScale {
+    fn default() -> Self {
+        ValueScale {
+            min: U7::default(),
+            max: U7::from_masked(U7::MAX),
+        }
+    }
+}
+
+impl ValueScale {
+    pub fn apply(&self, value: U7) -> U7 {
+        let (min, max) = (self.min.get() as f64, self.max.get() as f64);
+        let scaled = min + (max - min) * value.get() as f64 / U7::MAX as f64;
+        U7::from_masked(scaled.round() as u8)
+    }
+}
+
+impl ThruRoute {
+    /// The message to send on `output` for `message` arriving on `port`, if this route forwards
+    /// it.
+    pub fn apply(&self, port: &str, message: &MidiMessage) -> Option<MidiMessage> {
+        if self.input != port {
+            return None;
+        }
+        if !self.kinds.is_empty()
+            && !RouteKind::of(message).is_some_and(|kind| self.kinds.contains(&kind))
+        {
+            return None;
+        }
+        // Messages without a channel pass the channel filter; only `kinds` drops them.
+        if let (Some(wanted), Some(channel)) = (self.channel, message.channel()) {
+            if wanted != channel {
+                return None;
+            }
+        }
+        let message = match self.remap_channel {
+            Some(channel) => message.clone().with_channel(channel),
+            None => message.clone(),
+        };
+        Some(match message {
+            MidiMessage::ControlChange(cc) => MidiMessage::ControlChange(ControlChange {
+                control_number: self
+                    .cc_remap
+                    .iter()
+                    .find(|remap| remap.from == cc.control_number)
+                    .map_or(cc.control_number, |remap| remap.to),
+                value: self.scale.apply(cc.value),
+                ..cc
+            }),
+            message => message,
+        })
+    }
+}
+
+/// Replaces the routes messages are forwarded along.
+pub fn set_routes(routes: Vec<ThruRoute>) {
+    ROUTES.with(|current| *current.borrow_mut() = routes);
+}
+
+/// Forwards `message`, received on `port`, along every route starting there.
+pub fn forward(port: &str, message: &MidiMessage) {
+    let outgoing: Vec<(String, MidiMessage)> = ROUTES.with(|routes| {
+        routes
+            .borrow()
+            .iter()
+            .filter_map(|route| Some((route.output.clone(), route.apply(port, message)?)))
+            .collect()
+    });
+    for (output, message) in outgoing {
+        // Bank select only matters for `BankProgramChange`, which never arrives from an input.
+        let bytes = message.as_u8_with(BankSelectMode::default());
+        if let Err(err) = connection::send(&output, &bytes) {
+            log::error!("Error forwarding {} to {}: {}", message.kind(), output, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::clock::SongPosition;
+    use crate::midi::note::NoteOn;
+    use crate::midi::program_change::ProgramChange;
+    use crate::midi::sysex::SysEx;
+    use crate::midi::transport::{set_transport, LoopbackTransport};
+
+    fn channel(number: i32) -> MidiChannel {
+        MidiChannel::from_number(number).unwrap()
+    }
+
+    fn u7(value: u8) -> U7 {
+        U7::new(value).unwrap()
+    }
+
+    fn route() -> ThruRoute {
+        ThruRoute {
+            input: "Keyboard".to_string(),
+            output: "Synth".to_string(),
+            ..ThruRoute::default()
+        }
+    }
+
+    fn control(channel_number: i32, control_number: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange(ControlChange {
+            channel: channel(channel_number),
+            control_number: u7(control_number),
+            value: u7(value),
+        })
+    }
+
+    fn note_on(channel_number: i32, note: u8) -> MidiMessage {
+        MidiMessage::NoteOn(NoteOn {
+            channel: channel(channel_number),
+            note: u7(note),
+            velocity: u7(100),
+        })
+    }
+
+    fn scale(min: u8, max: u8) -> ValueScale {
+        ValueScale {
+            min: u7(min),
+            max: u7(max),
+        }
+    }
+
+    #[test]
+    fn forwards_only_from_its_input() {
+        let message = note_on(1, 60);
+        assert_eq!(route().apply("Keyboard", &message), Some(message.clone()));
+        assert_eq!(route().apply("Pedal", &message), None);
+    }
+
+    #[test]
+    fn filters_by_kind() {
+        let route = ThruRoute {
+            kinds: vec![RouteKind::NoteOn, RouteKind::TimingClock],
+            ..route()
+        };
+        assert!(route.apply("Keyboard", &note_on(1, 60)).is_some());
+        assert!(route.apply("Keyboard", &MidiMessage::TimingClock).is_some());
+        assert_eq!(route.apply("Keyboard", &control(1, 7, 100)), None);
+        assert_eq!(route.apply("Keyboard", &MidiMessage::Start), None);
+    }
+
+    #[test]
+    fn filters_by_channel() {
+        let route = ThruRoute {
+            channel: Some(channel(2)),
+            ..route()
+        };
+        assert!(route.apply("Keyboard", &note_on(2, 60)).is_some());
+        assert_eq!(route.apply("Keyboard", &note_on(3, 60)), None);
+        // Messages without a channel are not held back by the channel filter.
+        for message in [
+            MidiMessage::SysEx(SysEx::default()),
+            MidiMessage::TimingClock,
+            MidiMessage::SongPosition(SongPosition::default()),
+        ] {
+            assert_eq!(route.apply("Keyboard", &message), Some(message.clone()));
+        }
+        let route = ThruRoute {
+            kinds: vec![RouteKind::NoteOn],
+            ..route
+        };
+        assert_eq!(route.apply("Keyboard", &MidiMessage::TimingClock), None);
+    }
+
+    #[test]
+    fn remaps_the_channel() {
+        let route = ThruRoute {
+            remap_channel: Some(channel(10)),
+            ..route()
+        };
+        assert_eq!(
+            route.apply("Keyboard", &note_on(1, 60)),
+            Some(note_on(10, 60))
+        );
+        assert_eq!(
+            route.apply(
+                "Keyboard",
+                &MidiMessage::ProgramChange(ProgramChange {
+                    channel: channel(4),
+                    program: u7(5),
+                })
+            ),
+            Some(MidiMessage::ProgramChange(ProgramChange {
+                channel: channel(10),
+                program: u7(5),
+            }))
+        );
+        assert_eq!(
+            route.apply("Keyboard", &MidiMessage::Start),
+            Some(MidiMessage::Start)
+        );
+    }
+
+    #[test]
+    fn remaps_controllers() {
+        let route = ThruRoute {
+            cc_remap: vec![
+                CcRemap {
+                    from: u7(1),
+                    to: u7(11),
+                },
+                CcRemap {
+                    from: u7(1),
+                    to: u7(12),
+                },
+            ],
+            ..route()
+        };
+        assert_eq!(
+            route.apply("Keyboard", &control(1, 1, 64)),
+            Some(control(1, 11, 64))
+        );
+        assert_eq!(
+            route.apply("Keyboard", &control(1, 2, 64)),
+            Some(control(1, 2, 64))
+        );
+    }
+
+    #[test]
+    fn scales_controller_values() {
+        assert_eq!(ValueScale::default().apply(u7(0)), u7(0));
+        assert_eq!(ValueScale::default().apply(u7(64)), u7(64));
+        assert_eq!(ValueScale::default().apply(u7(127)), u7(127));
+
+        let narrow = scale(20, 40);
+        assert_eq!(narrow.apply(u7(0)), u7(20));
+        assert_eq!(narrow.apply(u7(64)), u7(30));
+        assert_eq!(narrow.apply(u7(127)), u7(40));
+
+        let inverted = scale(127, 0);
+        assert_eq!(inverted.apply(u7(0)), u7(127));
+        assert_eq!(inverted.apply(u7(27)), u7(100));
+        assert_eq!(inverted.apply(u7(127)), u7(0));
+
+        let fixed = scale(90, 90);
+        assert_eq!(fixed.apply(u7(0)), u7(90));
+        assert_eq!(fixed.apply(u7(127)), u7(90));
+
+        let route = ThruRoute {
+            scale: inverted,
+            ..route()
+        };
+        assert_eq!(
+            route.apply("Keyboard", &control(1, 7, 0)),
+            Some(control(1, 7, 127))
+        );
+        assert_eq!(route.apply("Keyboard", &note_on(1, 0)), Some(note_on(1, 0)));
+    }
+
+    #[test]
+    fn forwards_along_every_route_from_the_input() {
+        let transport = LoopbackTransport::new(&["Synth", "Pedal"]);
+        set_transport(transport.clone());
+        set_routes(vec![
+            route(),
+            ThruRoute {
+                output: "Pedal".to_string(),
+                remap_channel: Some(channel(2)),
+                ..route()
+            },
+            ThruRoute {
+                input: "Other".to_string(),
+                ..route()
+            },
+        ]);
+        forward("Keyboard", &note_on(1, 60));
+        assert_eq!(transport.sent_to("Synth"), vec![vec![0x90, 60, 100]]);
+        assert_eq!(transport.sent_to("Pedal"), vec![vec![0x91, 60, 100]]);
+        set_routes(vec![]);
+    }
+}
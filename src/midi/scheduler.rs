@@ -0,0 +1,475 @@
+//! Non-blocking playback of message sequences.
+//!
+//! A sequence is first turned into messages stamped with their offset from the sequence start,
+//! then played by a task on the browser's event loop. Each message is due at a fixed offset from
+//! the start, so timer jitter on one step never shifts the steps after it.
+//!
+//! Where the transport can schedule sends itself, as Web MIDI can, messages bound for a port are
+//! handed over with their timestamp a short while before they are due, and the task keeps track
+//! of progress and of what has to go through the clock. Only that look-ahead window is ever in
+//! the transport's hands, so cancelling works even where it cannot drop scheduled messages.
+
+use std::cell::Cell;
+use std::future::Future;
+
+#[cfg(not(test))]
+use gloo_timers::future::TimeoutFuture;
+
+use crate::midi::bank_select::BankSelectMode;
+use crate::midi::clock::now;
+use crate::midi::connection;
+use crate::midi::device::DeviceRef;
+use crate::midi::error::MidiError;
+use crate::midi::midi_message::{port_message, send_midi_message, MidiMessage, PresetMessage};
+use crate::midi::transport::with_transport;
+
+/// How long before they are due messages are handed to a transport that schedules ahead. Long
+/// enough to cover timer jitter on a busy page, short enough that little is left to go out after
+/// a cancel where the transport cannot take it back.
+const LOOK_AHEAD_MS: f64 = 100.0;
+
+thread_local! {
+    /// Bumped whenever a sequence starts or is cancelled; a playing task stops as soon as it sees
+    /// that the generation it started with is no longer current.
+    static GENERATION: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A message, the output it goes to and when to send it, in milliseconds after the sequence
+/// starts. `step` is the message's position in the sequence it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledMessage {
+    pub step: usize,
+    pub at: f64,
+    pub device: DeviceRef,
+    pub bank_select: BankSelectMode,
+    pub message: MidiMessage,
+}
+
+/// A step of a sequence that could not be sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendFailure {
+    pub step: usize,
+    pub device: DeviceRef,
+    pub error: MidiError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Progress {
+    pub sent: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    pub fn is_done(&self) -> bool {
+        self.sent >= self.total
+    }
+
+    /// Share of the sequence sent so far, from 0 to 100.
+    pub fn percent(&self) -> f64 {
+        match self.total {
+            0 => 100.0,
+            total => self.sent as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+/// Stamps each message with its send time and output, which is `device` unless the message
+/// overrides it. Delays only move the time forward and empty entries are dropped.
+pub fn schedule(
+    device: &DeviceRef,
+    messages: Vec<PresetMessage>,
+    bank_select: impl Fn(&DeviceRef) -> BankSelectMode,
+) -> Vec<ScheduledMessage> {
+    let mut at = 0.0;
+    let mut scheduled = vec![];
+    for (i, step) in messages.into_iter().enumerate() {
+        let device = step.device_or(device);
+        match step.message {
+            MidiMessage::Delay(delay) => at += delay as f64,
+            MidiMessage::Empty => (),
+            message => scheduled.push(ScheduledMessage {
+                step: i,
+                at,
+                bank_select: bank_select(&device),
+                device,
+                message,
+            }),
+        }
+    }
+    scheduled
+}
+
+/// Plays `messages` on `device` in the background, calling `on_progress` after every step and
+/// `on_error` for every step that fails to send. Starting a sequence cancels the one currently
+/// playing.
+pub fn play(
+    device: &DeviceRef,
+    messages: Vec<PresetMessage>,
+    bank_select: impl Fn(&DeviceRef) -> BankSelectMode,
+    on_progress: impl Fn(Progress) + 'static,
+    on_error: impl Fn(SendFailure) + 'static,
+) {
+    let generation = cancel();
+    let scheduled = schedule(device, messages, bank_select);
+    let total = scheduled.len();
+    on_progress(Progress { sent: 0, total });
+    let ahead = with_transport(|transport| transport.schedules_ahead());
+    spawn(async move {
+        let started_at = now();
+        let mut handed_over = vec![None; total];
+        let mut next_hand_over = 0;
+        for sent in 0..total {
+            if ahead {
+                // Refill the look-ahead window, which always reaches this step once woken.
+                wait_until(started_at + scheduled[sent].at - LOOK_AHEAD_MS).await;
+                if GENERATION.with(Cell::get) != generation {
+                    return;
+                }
+                let window_end = now() - started_at + LOOK_AHEAD_MS;
+                while let Some(step) = scheduled
+                    .get(next_hand_over)
+                    .filter(|step| step.at <= window_end)
+                {
+                    handed_over[next_hand_over] = hand_over(step, started_at + step.at);
+                    next_hand_over += 1;
+                }
+            }
+            let step = scheduled[sent].clone();
+            wait_until(started_at + step.at).await;
+            if GENERATION.with(Cell::get) != generation {
+                return;
+            }
+            let result = match handed_over[sent].take() {
+                Some(result) => result.map(|(port, bytes)| connection::sent(&port, &bytes)),
+                None => send_midi_message(&step.device, step.bank_select, step.message),
+            };
+            if let Err(error) = result {
+                log::error!(
+                    "Error sending step {} to {}: {}",
+                    step.step,
+                    step.device,
+                    error
+                );
+                on_error(SendFailure {
+                    step: step.step,
+                    device: step.device,
+                    error,
+                });
+            }
+            on_progress(Progress {
+                sent: sent + 1,
+                total,
+            });
+        }
+    });
+}
+
+/// Passes `step` to the transport to send at `timestamp`, if it is written straight to a port.
+/// Returns what was handed over, or `None` if the step has to be sent when it is due.
+fn hand_over(
+    step: &ScheduledMessage,
+    timestamp: f64,
+) -> Option<Result<(String, Vec<u8>), MidiError>> {
+    let message = port_message(&step.device, step.bank_select, &step.message)?;
+    Some(message.and_then(|(port, bytes)| {
+        connection::send_at(&port, &bytes, timestamp)?;
+        Ok((port, bytes))
+    }))
+}
+
+/// Aborts the remaining steps of the sequence currently playing, if any, and asks the transport to
+/// drop those already handed to it. Returns the new generation.
+pub fn cancel() -> u64 {
+    with_transport(|transport| transport.clear_scheduled());
+    GENERATION.with(|generation| {
+        generation.set(generation.get() + 1);
+        generation.get()
+    })
+}
+
+/// Runs `task` on the browser's event loop.
+#[cfg(not(test))]
+fn spawn(task: impl Future<Output = ()> + 'static) {
+    wasm_bindgen_futures::spawn_local(task)
+}
+
+/// Tests have no event loop, so `task` runs to completion before `play` returns.
+#[cfg(test)]
+fn spawn(task: impl Future<Output = ()> + 'static) {
+    futures_lite::future::block_on(task)
+}
+
+/// Sleeps until `deadline` on the `now()` clock. Timers only have millisecond granularity, so
+/// the remaining fraction is picked up by re-checking the clock.
+#[cfg(not(test))]
+async fn wait_until(deadline: f64) {
+    loop {
+        let remaining = deadline - now();
+        if remaining <= 0.0 {
+            return;
+        }
+        TimeoutFuture::new(remaining.floor() as u32).await;
+    }
+}
+
+#[cfg(test)]
+async fn wait_until(deadline: f64) {
+    let remaining = deadline - now();
+    if remaining > 0.0 {
+        std::thread::sleep(std::time::Duration::from_secs_f64(remaining / 1000.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::midi::bank_select::BankProgramChange;
+    use crate::midi::channel::MidiChannel;
+    use crate::midi::control_change::ControlChange;
+    use crate::midi::program_change::ProgramChange;
+    use crate::midi::transport::{set_transport, LoopbackTransport};
+    use crate::midi::value::{U14, U7};
+
+    fn program(program: u8) -> MidiMessage {
+        MidiMessage::ProgramChange(ProgramChange {
+            channel: MidiChannel::default(),
+            program: U7::new(program).unwrap(),
+        })
+    }
+
+    fn cc(control_number: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange(ControlChange {
+            channel: MidiChannel::default(),
+            control_number: U7::new(control_number).unwrap(),
+            value: U7::new(value).unwrap(),
+        })
+    }
+
+    fn device(name: &str) -> DeviceRef {
+        DeviceRef::from(name.to_string())
+    }
+
+    fn steps(messages: Vec<MidiMessage>) -> Vec<PresetMessage> {
+        messages.into_iter().map(PresetMessage::from).collect()
+    }
+
+    /// Plays `messages` on "Synth" through a fresh loopback, returning the transport, the
+    /// progress reported and the failures.
+    fn play_on_loopback(
+        outputs: &[&str],
+        messages: Vec<PresetMessage>,
+    ) -> (LoopbackTransport, Vec<Progress>, Vec<SendFailure>) {
+        let transport = LoopbackTransport::new(outputs);
+        set_transport(transport.clone());
+        let progress = Rc::new(RefCell::new(vec![]));
+        let failures = Rc::new(RefCell::new(vec![]));
+        play(
+            &device("Synth"),
+            messages,
+            |_| BankSelectMode::default(),
+            {
+                let progress = progress.clone();
+                move |update| progress.borrow_mut().push(update)
+            },
+            {
+                let failures = failures.clone();
+                move |failure| failures.borrow_mut().push(failure)
+            },
+        );
+        let progress = progress.borrow().clone();
+        let failures = failures.borrow().clone();
+        (transport, progress, failures)
+    }
+
+    #[test]
+    fn schedules_steps_at_their_offsets() {
+        let scheduled = schedule(
+            &device("Synth"),
+            steps(vec![
+                program(1),
+                MidiMessage::Delay(30),
+                MidiMessage::Empty,
+                cc(7, 100),
+                MidiMessage::Delay(20),
+                MidiMessage::Delay(5),
+                program(2),
+            ]),
+            |_| BankSelectMode::default(),
+        );
+        let at: Vec<(usize, f64)> = scheduled.iter().map(|step| (step.step, step.at)).collect();
+        assert_eq!(at, vec![(0, 0.0), (3, 30.0), (6, 55.0)]);
+    }
+
+    #[test]
+    fn plays_steps_in_order_and_on_time() {
+        let (transport, progress, failures) = play_on_loopback(
+            &["Synth"],
+            steps(vec![
+                program(1),
+                MidiMessage::Delay(30),
+                cc(7, 100),
+                cc(10, 64),
+                MidiMessage::Delay(20),
+                program(2),
+            ]),
+        );
+        let sent = transport.sent();
+        let bytes: Vec<Vec<u8>> = sent.iter().map(|message| message.bytes.clone()).collect();
+        assert_eq!(
+            bytes,
+            vec![
+                vec![0xC0, 1],
+                vec![0xB0, 7, 100],
+                vec![0xB0, 10, 64],
+                vec![0xC0, 2]
+            ]
+        );
+        assert!(sent.iter().all(|message| message.port == "Synth"));
+        // Sleeps never end early, and each delay counts from the sequence start.
+        let after_start = |i: usize| sent[i].at - sent[0].at;
+        assert!((30.0..45.0).contains(&after_start(1)), "{}", after_start(1));
+        assert!(after_start(2) - after_start(1) < 5.0);
+        assert!((50.0..65.0).contains(&after_start(3)), "{}", after_start(3));
+        assert!(failures.is_empty());
+        assert_eq!(progress.first(), Some(&Progress { sent: 0, total: 4 }));
+        assert_eq!(progress.last(), Some(&Progress { sent: 4, total: 4 }));
+        assert_eq!(progress.len(), 5);
+    }
+
+    #[test]
+    fn sends_steps_to_the_device_they_override() {
+        let mut messages = steps(vec![program(1), program(2), program(3)]);
+        messages[1].device = Some(device("Pedal"));
+        let (transport, _, failures) = play_on_loopback(&["Synth", "Pedal"], messages);
+        assert_eq!(
+            transport.sent_to("Synth"),
+            vec![vec![0xC0, 1], vec![0xC0, 3]]
+        );
+        assert_eq!(transport.sent_to("Pedal"), vec![vec![0xC0, 2]]);
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn reports_failed_steps_and_carries_on() {
+        let mut messages = steps(vec![
+            program(1),
+            MidiMessage::Delay(5),
+            program(2),
+            program(3),
+        ]);
+        messages[2].device = Some(device("Unplugged"));
+        let (transport, progress, failures) = play_on_loopback(&["Synth"], messages);
+        assert_eq!(
+            transport.sent_to("Synth"),
+            vec![vec![0xC0, 1], vec![0xC0, 3]]
+        );
+        assert_eq!(
+            failures,
+            vec![SendFailure {
+                step: 2,
+                device: device("Unplugged"),
+                error: MidiError::PortNotFound("Unplugged".to_string()),
+            }]
+        );
+        assert_eq!(progress.last(), Some(&Progress { sent: 3, total: 3 }));
+    }
+
+    #[test]
+    fn uses_the_bank_select_mode_of_each_device() {
+        let transport = LoopbackTransport::new(&["Synth", "Pedal"]);
+        set_transport(transport.clone());
+        let bank_program = MidiMessage::BankProgramChange(BankProgramChange {
+            channel: MidiChannel::default(),
+            preset: U14::new(2 * 128 + 5).unwrap(),
+        });
+        let mut messages = steps(vec![bank_program.clone(), bank_program]);
+        messages[1].device = Some(device("Pedal"));
+        play(
+            &device("Synth"),
+            messages,
+            |device| match device.port_name().as_deref() {
+                Some("Pedal") => BankSelectMode::Lsb,
+                _ => BankSelectMode::Msb,
+            },
+            |_| (),
+            |failure| panic!("{:?}", failure),
+        );
+        assert_eq!(transport.sent_to("Synth"), vec![vec![0xB0, 0, 2, 0xC0, 5]]);
+        assert_eq!(transport.sent_to("Pedal"), vec![vec![0xB0, 32, 2, 0xC0, 5]]);
+    }
+
+    #[test]
+    fn hands_over_only_what_is_due_soon() {
+        let transport = LoopbackTransport::new(&["Synth"]).scheduling_ahead();
+        set_transport(transport.clone());
+        let handed_over = Rc::new(RefCell::new(vec![]));
+        play(
+            &device("Synth"),
+            steps(vec![
+                program(1),
+                MidiMessage::Delay(50),
+                program(2),
+                MidiMessage::Delay(200),
+                program(3),
+            ]),
+            |_| BankSelectMode::default(),
+            {
+                let transport = transport.clone();
+                let handed_over = handed_over.clone();
+                move |_| handed_over.borrow_mut().push(transport.sent().len())
+            },
+            |failure| panic!("{:?}", failure),
+        );
+        // Progress is reported as each step falls due. The second step is handed over with the
+        // first, but the last one only once it is within the look-ahead window.
+        assert_eq!(*handed_over.borrow(), vec![0, 2, 2, 3]);
+        let sent = transport.sent();
+        let after_start: Vec<f64> = sent
+            .iter()
+            .map(|message| (message.at - sent[0].at).round())
+            .collect();
+        assert_eq!(after_start, vec![0.0, 50.0, 250.0]);
+    }
+
+    #[test]
+    fn cancelling_stops_the_steps_still_to_come() {
+        for transport in [
+            LoopbackTransport::new(&["Synth"]),
+            LoopbackTransport::new(&["Synth"]).scheduling_ahead(),
+        ] {
+            set_transport(transport.clone());
+            let progress = Rc::new(RefCell::new(vec![]));
+            play(
+                &device("Synth"),
+                steps(vec![
+                    program(1),
+                    MidiMessage::Delay(200),
+                    program(2),
+                    MidiMessage::Delay(200),
+                    program(3),
+                ]),
+                |_| BankSelectMode::default(),
+                {
+                    let progress = progress.clone();
+                    move |update: Progress| {
+                        progress.borrow_mut().push(update);
+                        if update.sent == 1 {
+                            cancel();
+                        }
+                    }
+                },
+                |failure| panic!("{:?}", failure),
+            );
+            // The transport cannot take back what it was given, so nothing after the first step
+            // may have been handed over yet.
+            assert_eq!(transport.sent_to("Synth"), vec![vec![0xC0, 1]]);
+            assert_eq!(
+                progress.borrow().last(),
+                Some(&Progress { sent: 1, total: 3 })
+            );
+        }
+    }
+}
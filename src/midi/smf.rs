@@ -0,0 +1,608 @@
+//! Standard MIDI File (SMF) import and export.
+//!
+//! Export writes a format 0 file with a single track. The file's timing is chosen so that one
+//! tick is one millisecond, which lets every `MidiMessage::Delay` become a delta time as-is.
+//! Import accepts format 0 and 1 files, merges all tracks in time order and turns the delta
+//! times back into delays using the file's tempo map.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::midi::bank_select::BankSelectMode;
+use crate::midi::midi_message::MidiMessage;
+use crate::midi::parser::{data_length, MidiParser};
+use crate::midi::sysex::{SYSEX_END, SYSEX_START};
+
+pub const HEADER_CHUNK: &[u8; 4] = b"MThd";
+pub const TRACK_CHUNK: &[u8; 4] = b"MTrk";
+pub const META_EVENT: u8 = 0xFF;
+pub const META_TRACK_NAME: u8 = 0x03;
+pub const META_END_OF_TRACK: u8 = 0x2F;
+pub const META_SET_TEMPO: u8 = 0x51;
+/// Ticks per quarter note written on export.
+pub const EXPORT_DIVISION: u16 = 1000;
+/// Microseconds per quarter note written on export (60 BPM), making one tick one millisecond.
+pub const EXPORT_TEMPO: u32 = 1_000_000;
+/// Tempo assumed until a file sets its own (120 BPM).
+pub const DEFAULT_TEMPO: u32 = 500_000;
+/// Largest variable-length quantity, which takes four bytes. Longer delays are cut to this on
+/// export, which is still over 74 hours.
+pub const MAX_VLQ: u32 = 0x0FFF_FFFF;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmfError {
+    MissingHeader,
+    UnsupportedFormat(u16),
+    InvalidDivision,
+    UnexpectedEnd,
+    InvalidEvent { offset: usize, byte: u8 },
+}
+
+impl Display for SmfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmfError::MissingHeader => write!(f, "Not a Standard MIDI File"),
+            SmfError::UnsupportedFormat(format) => {
+                write!(f, "MIDI file format {} is not supported", format)
+            }
+            SmfError::InvalidDivision => write!(f, "MIDI file has no valid time division"),
+            SmfError::UnexpectedEnd => write!(f, "MIDI file ends unexpectedly"),
+            SmfError::InvalidEvent { offset, byte } => {
+                write!(f, "Invalid event byte {:02X} at offset {}", byte, offset)
+            }
+        }
+    }
+}
+
+impl Error for SmfError {}
+
+/// A message sequence as stored in a MIDI file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MidiFile {
+    pub name: Option<String>,
+    pub messages: Vec<MidiMessage>,
+}
+
+impl MidiFile {
+    /// Encodes the sequence as a format 0 file.
+    ///
+    /// Composite messages such as NRPN or bank program changes are written as the individual
+    /// events they are sent as, using `bank_select` for the latter. Clock tempo entries have no
+    /// file equivalent and are left out.
+    pub fn encode(&self, bank_select: BankSelectMode) -> Vec<u8> {
+        let mut track = vec![];
+        if let Some(name) = &self.name {
+            write_meta(&mut track, 0, META_TRACK_NAME, name.as_bytes());
+        }
+        write_meta(
+            &mut track,
+            0,
+            META_SET_TEMPO,
+            &EXPORT_TEMPO.to_be_bytes()[1..],
+        );
+
+        let mut delta: u64 = 0;
+        for message in &self.messages {
+            if let MidiMessage::Delay(delay) = message {
+                delta = delta.saturating_add(*delay);
+                continue;
+            }
+            for event in events(&message.as_u8_with(bank_select)) {
+                write_vlq(&mut track, delta);
+                track.extend(event);
+                delta = 0;
+            }
+        }
+        write_meta(&mut track, delta, META_END_OF_TRACK, &[]);
+
+        let mut bytes = HEADER_CHUNK.to_vec();
+        bytes.extend(6u32.to_be_bytes());
+        bytes.extend(0u16.to_be_bytes());
+        bytes.extend(1u16.to_be_bytes());
+        bytes.extend(EXPORT_DIVISION.to_be_bytes());
+        bytes.extend(TRACK_CHUNK);
+        bytes.extend((track.len() as u32).to_be_bytes());
+        bytes.extend(track);
+        bytes
+    }
+
+    /// Decodes a format 0 or 1 file. The first track name found becomes the sequence name.
+    pub fn parse(bytes: &[u8]) -> Result<MidiFile, SmfError> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(4).ok() != Some(HEADER_CHUNK.as_slice()) {
+            return Err(SmfError::MissingHeader);
+        }
+        let header_length = reader.u32()? as usize;
+        let mut header = Reader::new(reader.take(header_length)?);
+        let format = header.u16()?;
+        if format > 1 {
+            return Err(SmfError::UnsupportedFormat(format));
+        }
+        let track_count = header.u16()?;
+        let division = header.u16()?;
+
+        let mut events = vec![];
+        let mut tracks = 0;
+        while tracks < track_count && !reader.is_empty() {
+            let id = reader.take(4)?;
+            let length = reader.u32()? as usize;
+            let offset = reader.pos;
+            let chunk = reader.take(length)?;
+            // Unknown chunk types must be skipped.
+            if id != TRACK_CHUNK.as_slice() {
+                continue;
+            }
+            read_track(chunk, offset, &mut events)?;
+            tracks += 1;
+        }
+        // Stable, so events on the same tick keep their track order.
+        events.sort_by_key(|event| event.tick);
+
+        let mut file = MidiFile::default();
+        let mut clock = TickClock::new(division)?;
+        for event in events {
+            let elapsed = clock.advance(event.tick);
+            match event.kind {
+                EventKind::Tempo(tempo) => clock.tempo = tempo,
+                EventKind::TrackName(name) => {
+                    if file.name.is_none() {
+                        file.name = Some(name)
+                    }
+                }
+                EventKind::Message(message) => {
+                    if elapsed > 0 {
+                        file.messages.push(MidiMessage::Delay(elapsed));
+                    }
+                    clock.emitted += elapsed;
+                    file.messages.push(message);
+                }
+            }
+        }
+        Ok(file)
+    }
+}
+
+struct TrackEvent {
+    tick: u64,
+    kind: EventKind,
+}
+
+enum EventKind {
+    Message(MidiMessage),
+    Tempo(u32),
+    TrackName(String),
+}
+
+fn read_track(chunk: &[u8], offset: usize, events: &mut Vec<TrackEvent>) -> Result<(), SmfError> {
+    let mut reader = Reader::new(chunk);
+    let mut parser = MidiParser::new();
+    let mut running_status = None;
+    let mut tick = 0;
+    while !reader.is_empty() {
+        tick += reader.vlq()? as u64;
+        let byte = reader.u8()?;
+        let invalid = SmfError::InvalidEvent {
+            offset: offset + reader.pos - 1,
+            byte,
+        };
+        let messages = match byte {
+            META_EVENT => {
+                let kind = reader.u8()?;
+                let length = reader.vlq()? as usize;
+                let data = reader.take(length)?;
+                running_status = None;
+                match kind {
+                    META_END_OF_TRACK => break,
+                    META_SET_TEMPO if data.len() == 3 => events.push(TrackEvent {
+                        tick,
+                        kind: EventKind::Tempo(u32::from_be_bytes([0, data[0], data[1], data[2]])),
+                    }),
+                    META_TRACK_NAME => events.push(TrackEvent {
+                        tick,
+                        kind: EventKind::TrackName(String::from_utf8_lossy(data).to_string()),
+                    }),
+                    _ => (),
+                }
+                continue;
+            }
+            SYSEX_START => {
+                let length = reader.vlq()? as usize;
+                running_status = None;
+                let mut data = vec![SYSEX_START];
+                data.extend(reader.take(length)?);
+                parser.feed(&data)
+            }
+            // Either the continuation of a split SysEx or an escaped sequence of raw bytes.
+            SYSEX_END => {
+                let length = reader.vlq()? as usize;
+                running_status = None;
+                parser.feed(reader.take(length)?)
+            }
+            0x80..=0xEF => {
+                running_status = Some(byte);
+                let mut data = vec![byte];
+                data.extend(reader.take(data_length(byte))?);
+                parser.feed(&data)
+            }
+            0x00..=0x7F => {
+                let status = running_status.ok_or(invalid)?;
+                let mut data = vec![status, byte];
+                data.extend(reader.take(data_length(status) - 1)?);
+                parser.feed(&data)
+            }
+            _ => return Err(invalid),
+        };
+        events.extend(messages.into_iter().map(|message| TrackEvent {
+            tick,
+            kind: EventKind::Message(message),
+        }));
+    }
+    Ok(())
+}
+
+/// Converts absolute ticks into whole milliseconds, following tempo changes.
+struct TickClock {
+    /// Ticks per quarter note, or per second for SMPTE based files.
+    ticks: u64,
+    smpte: bool,
+    tempo: u32,
+    tick: u64,
+    micros: u64,
+    emitted: u64,
+}
+
+impl TickClock {
+    fn new(division: u16) -> Result<TickClock, SmfError> {
+        let (ticks, smpte) = if division & 0x8000 == 0 {
+            (division as u64, false)
+        } else {
+            // Negative frames per second in the high byte, ticks per frame in the low byte.
+            let fps = (-((division >> 8) as u8 as i8)) as u64;
+            (fps * (division & 0xFF) as u64, true)
+        };
+        if ticks == 0 {
+            return Err(SmfError::InvalidDivision);
+        }
+        Ok(TickClock {
+            ticks,
+            smpte,
+            tempo: DEFAULT_TEMPO,
+            tick: 0,
+            micros: 0,
+            emitted: 0,
+        })
+    }
+
+    /// Moves the clock to `tick`, returning the milliseconds not yet emitted as a delay.
+    fn advance(&mut self, tick: u64) -> u64 {
+        let per_unit = if self.smpte {
+            1_000_000
+        } else {
+            self.tempo as u64
+        };
+        self.micros += (tick - self.tick) * per_unit / self.ticks;
+        self.tick = tick;
+        ((self.micros + 500) / 1000).saturating_sub(self.emitted)
+    }
+}
+
+/// Splits wire bytes into file events. SysEx keeps its own framing, channel messages are
+/// written one per event and anything else is escaped with `F7`.
+fn events(bytes: &[u8]) -> Vec<Vec<u8>> {
+    match bytes.first() {
+        None => vec![],
+        Some(&SYSEX_START) => {
+            let mut event = vec![SYSEX_START];
+            write_vlq(&mut event, bytes.len() as u64 - 1);
+            event.extend(&bytes[1..]);
+            vec![event]
+        }
+        Some(0x80..=0xEF) => {
+            let mut events = vec![];
+            let mut rest = bytes;
+            while let Some(status) = rest.first() {
+                let (event, tail) = rest.split_at((1 + data_length(*status)).min(rest.len()));
+                events.push(event.to_vec());
+                rest = tail;
+            }
+            events
+        }
+        Some(_) => {
+            let mut event = vec![SYSEX_END];
+            write_vlq(&mut event, bytes.len() as u64);
+            event.extend(bytes);
+            vec![event]
+        }
+    }
+}
+
+fn write_meta(track: &mut Vec<u8>, delta: u64, kind: u8, data: &[u8]) {
+    write_vlq(track, delta);
+    track.extend([META_EVENT, kind]);
+    write_vlq(track, data.len() as u64);
+    track.extend(data);
+}
+
+/// Writes a variable-length quantity, seven bits per byte with the high bit as continuation.
+/// Values above `MAX_VLQ` are written as `MAX_VLQ`.
+fn write_vlq(bytes: &mut Vec<u8>, value: u64) {
+    let value = value.min(MAX_VLQ as u64) as u32;
+    let mut groups = vec![(value & 0x7F) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        groups.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], SmfError> {
+        let end = self
+            .pos
+            .checked_add(length)
+            .ok_or(SmfError::UnexpectedEnd)?;
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(SmfError::UnexpectedEnd)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SmfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SmfError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SmfError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a variable-length quantity of at most four bytes.
+    fn vlq(&mut self) -> Result<u32, SmfError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SmfError::InvalidEvent {
+            offset: self.pos - 1,
+            byte: self.bytes[self.pos - 1],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::channel::MidiChannel;
+    use crate::midi::note::{NoteOff, NoteOn};
+    use crate::midi::program_change::ProgramChange;
+    use crate::midi::sysex::SysEx;
+    use crate::midi::value::U7;
+
+    fn note_on(note: u8) -> MidiMessage {
+        MidiMessage::NoteOn(NoteOn {
+            channel: MidiChannel::default(),
+            note: U7::new(note).unwrap(),
+            velocity: U7::new(100).unwrap(),
+        })
+    }
+
+    fn note_off(note: u8) -> MidiMessage {
+        MidiMessage::NoteOff(NoteOff {
+            channel: MidiChannel::default(),
+            note: U7::new(note).unwrap(),
+            velocity: U7::new(64).unwrap(),
+        })
+    }
+
+    fn program(program: u8) -> MidiMessage {
+        MidiMessage::ProgramChange(ProgramChange {
+            channel: MidiChannel::default(),
+            program: U7::new(program).unwrap(),
+        })
+    }
+
+    fn vlq(value: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_vlq(&mut bytes, value);
+        bytes
+    }
+
+    #[test]
+    fn writes_and_reads_variable_length_quantities() {
+        // The examples from the Standard MIDI File specification.
+        let reference: [(u32, &[u8]); 12] = [
+            (0x00, &[0x00]),
+            (0x40, &[0x40]),
+            (0x7F, &[0x7F]),
+            (0x80, &[0x81, 0x00]),
+            (0x2000, &[0xC0, 0x00]),
+            (0x3FFF, &[0xFF, 0x7F]),
+            (0x4000, &[0x81, 0x80, 0x00]),
+            (0x10_0000, &[0xC0, 0x80, 0x00]),
+            (0x1F_FFFF, &[0xFF, 0xFF, 0x7F]),
+            (0x20_0000, &[0x81, 0x80, 0x80, 0x00]),
+            (0x800_0000, &[0xC0, 0x80, 0x80, 0x00]),
+            (MAX_VLQ, &[0xFF, 0xFF, 0xFF, 0x7F]),
+        ];
+        for (value, bytes) in reference {
+            assert_eq!(vlq(value as u64), bytes, "{:X}", value);
+            assert_eq!(Reader::new(bytes).vlq(), Ok(value), "{:X}", value);
+        }
+    }
+
+    #[test]
+    fn saturates_quantities_the_reader_would_reject() {
+        assert_eq!(vlq(MAX_VLQ as u64 + 1), vlq(MAX_VLQ as u64));
+        assert_eq!(vlq(u64::MAX), vec![0xFF, 0xFF, 0xFF, 0x7F]);
+        assert!(Reader::new(&[0x81, 0x80, 0x80, 0x80, 0x00]).vlq().is_err());
+    }
+
+    #[test]
+    fn exports_reference_file() {
+        let file = MidiFile {
+            name: Some("A".to_string()),
+            messages: vec![program(5), MidiMessage::Delay(200), note_on(60)],
+        };
+        #[rustfmt::skip]
+        let expected = [
+            b'M', b'T', b'h', b'd', 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x03, 0xE8,
+            b'M', b'T', b'r', b'k', 0x00, 0x00, 0x00, 0x18,
+            0x00, 0xFF, 0x03, 0x01, b'A',
+            0x00, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40,
+            0x00, 0xC0, 0x05,
+            0x81, 0x48, 0x90, 0x3C, 0x64,
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        assert_eq!(file.encode(BankSelectMode::Msb), expected);
+    }
+
+    #[test]
+    fn imports_format_1_reference_file() {
+        // Two tracks at 96 ticks per quarter note: a tempo map going from 120 to 60 BPM after
+        // the first quarter note, and notes using running status.
+        #[rustfmt::skip]
+        let bytes = [
+            b'M', b'T', b'h', b'd', 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x02, 0x00, 0x60,
+            b'M', b'T', b'r', b'k', 0x00, 0x00, 0x00, 0x1A,
+            0x00, 0xFF, 0x03, 0x04, b'T', b'e', b's', b't',
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20,
+            0x60, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40,
+            0x00, 0xFF, 0x2F, 0x00,
+            b'M', b'T', b'r', b'k', 0x00, 0x00, 0x00, 0x15,
+            0x00, 0x90, 0x3C, 0x64,
+            0x00, 0x3E, 0x64,
+            0x60, 0x80, 0x3C, 0x40,
+            0x00, 0x3E, 0x40,
+            0x60, 0xC0, 0x05,
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        assert_eq!(
+            MidiFile::parse(&bytes),
+            Ok(MidiFile {
+                name: Some("Test".to_string()),
+                messages: vec![
+                    note_on(60),
+                    note_on(62),
+                    MidiMessage::Delay(500),
+                    note_off(60),
+                    note_off(62),
+                    MidiMessage::Delay(1000),
+                    program(5),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn imports_split_and_escaped_sysex() {
+        // Format 0 at the default tempo, where 1000 ticks per quarter note make a tick 0.5 ms.
+        #[rustfmt::skip]
+        let bytes = [
+            b'M', b'T', b'h', b'd', 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x03, 0xE8,
+            b'M', b'T', b'r', b'k', 0x00, 0x00, 0x00, 0x12,
+            0x00, 0xF0, 0x02, 0x7D, 0x01,
+            0x0A, 0xF7, 0x02, 0x02, 0xF7,
+            0x00, 0xF7, 0x01, 0xF8,
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        assert_eq!(
+            MidiFile::parse(&bytes),
+            Ok(MidiFile {
+                name: None,
+                messages: vec![
+                    MidiMessage::Delay(5),
+                    MidiMessage::SysEx(
+                        SysEx::new(vec![SYSEX_START, 0x7D, 0x01, 0x02, SYSEX_END]).unwrap()
+                    ),
+                    MidiMessage::TimingClock,
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert_eq!(MidiFile::parse(b"RIFF"), Err(SmfError::MissingHeader));
+        let header = |format: u16, division: u16| {
+            let mut bytes = HEADER_CHUNK.to_vec();
+            bytes.extend(6u32.to_be_bytes());
+            bytes.extend(format.to_be_bytes());
+            bytes.extend(1u16.to_be_bytes());
+            bytes.extend(division.to_be_bytes());
+            bytes
+        };
+        assert_eq!(
+            MidiFile::parse(&header(2, 96)),
+            Err(SmfError::UnsupportedFormat(2))
+        );
+        assert_eq!(
+            MidiFile::parse(&header(0, 0)),
+            Err(SmfError::InvalidDivision)
+        );
+        let mut truncated = header(0, 96);
+        truncated.extend(TRACK_CHUNK);
+        truncated.extend(8u32.to_be_bytes());
+        truncated.extend([0x00, 0x90, 0x3C]);
+        assert_eq!(MidiFile::parse(&truncated), Err(SmfError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn round_trips_sequences() {
+        let file = MidiFile {
+            name: Some("Sequence".to_string()),
+            messages: vec![
+                program(3),
+                MidiMessage::Delay(1),
+                note_on(60),
+                note_on(64),
+                MidiMessage::Delay(MAX_VLQ as u64),
+                note_off(60),
+                MidiMessage::SysEx(SysEx::new(vec![SYSEX_START, 0x7D, 0x10, SYSEX_END]).unwrap()),
+                MidiMessage::Delay(250),
+                MidiMessage::TimingClock,
+            ],
+        };
+        assert_eq!(MidiFile::parse(&file.encode(BankSelectMode::Msb)), Ok(file));
+    }
+
+    #[test]
+    fn saturates_delays_too_long_for_a_file() {
+        let file = MidiFile {
+            name: None,
+            messages: vec![
+                MidiMessage::Delay(u64::MAX),
+                MidiMessage::Delay(5),
+                note_on(60),
+            ],
+        };
+        assert_eq!(
+            MidiFile::parse(&file.encode(BankSelectMode::Msb)).map(|file| file.messages),
+            Ok(vec![MidiMessage::Delay(MAX_VLQ as u64), note_on(60)])
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::midi::program_change::AsU8;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub const SYSEX_START: u8 = 0xF0;
+pub const SYSEX_END: u8 = 0xF7;
+
+/// A System Exclusive message, stored including the `F0` and `F7` framing bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SysEx {
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SysExError {
+    Empty,
+    MissingStart,
+    MissingEnd,
+    InvalidDataByte { index: usize, byte: u8 },
+    InvalidHex(String),
+}
+
+impl Display for SysExError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SysExError::Empty => write!(f, "SysEx message is empty"),
+            SysExError::MissingStart => write!(f, "SysEx message must start with F0"),
+            SysExError::MissingEnd => write!(f, "SysEx message must end with F7"),
+            SysExError::InvalidDataByte { index, byte } => {
+                write!(f, "Byte {} ({:02X}) is not a 7-bit data byte", index, byte)
+            }
+            SysExError::InvalidHex(token) => write!(f, "'{}' is not a hex byte", token),
+        }
+    }
+}
+
+impl Error for SysExError {}
+
+impl Default for SysEx {
+    fn default() -> Self {
+        SysEx {
+            data: vec![SYSEX_START, SYSEX_END],
+        }
+    }
+}
+
+impl SysEx {
+    pub fn new(data: Vec<u8>) -> Result<SysEx, SysExError> {
+        SysEx::validate(&data)?;
+        Ok(SysEx { data })
+    }
+
+    /// Parses hex bytes such as `F0 00 01 74 F7`, `0xF0,0x7E` or `F07EF7`.
+    pub fn from_hex(hex: &str) -> Result<SysEx, SysExError> {
+        let mut data = vec![];
+        for token in hex
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|t| !t.is_empty())
+        {
+            let digits = token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .unwrap_or(token);
+            // Checked first, so the pairs below never split a multi-byte character and no sign
+            // gets through to `from_str_radix`.
+            if !digits.chars().all(|c| c.is_ascii_hexdigit())
+                || digits.is_empty()
+                || (digits.len() > 1 && digits.len() % 2 != 0)
+            {
+                return Err(SysExError::InvalidHex(token.to_string()));
+            }
+            if digits.len() == 1 {
+                data.push(parse_hex_byte(digits, token)?);
+                continue;
+            }
+            for i in (0..digits.len()).step_by(2) {
+                data.push(parse_hex_byte(&digits[i..i + 2], token)?);
+            }
+        }
+        SysEx::new(data)
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.data
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    pub fn validate(data: &[u8]) -> Result<(), SysExError> {
+        if data.is_empty() {
+            return Err(SysExError::Empty);
+        }
+        if data[0] != SYSEX_START {
+            return Err(SysExError::MissingStart);
+        }
+        if data.len() < 2 || data[data.len() - 1] != SYSEX_END {
+            return Err(SysExError::MissingEnd);
+        }
+        match data[1..data.len() - 1].iter().position(|b| *b > 0x7F) {
+            Some(i) => Err(SysExError::InvalidDataByte {
+                index: i + 1,
+                byte: data[i + 1],
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_hex_byte(digits: &str, token: &str) -> Result<u8, SysExError> {
+    u8::from_str_radix(digits, 16).map_err(|_| SysExError::InvalidHex(token.to_string()))
+}
+
+impl Display for SysEx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SysEx: {}", self.to_hex())
+    }
+}
+
+impl AsU8 for SysEx {
+    fn as_u8(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_in_every_accepted_format() {
+        let expected = vec![0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7];
+        for hex in [
+            "F0 7E 7F 06 01 F7",
+            "f0 7e 7f 06 01 f7",
+            "0xF0,0x7E,0x7F,0x06,0x01,0xF7",
+            "F07E7F0601F7",
+            "F0 7E 7F 6 1 F7",
+            "  F0, 7E7F 06\n01 F7 ",
+        ] {
+            assert_eq!(SysEx::from_hex(hex).unwrap().data, expected, "{}", hex);
+        }
+    }
+
+    #[test]
+    fn rejects_non_hex_digits() {
+        for hex in [
+            "F0 aé1 F7",
+            "F0 é F7",
+            "F0 +1 F7",
+            "F0 -1 F7",
+            "F0 0x F7",
+            "F0 7G F7",
+        ] {
+            assert!(
+                matches!(SysEx::from_hex(hex), Err(SysExError::InvalidHex(_))),
+                "{}",
+                hex
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_odd_length_runs() {
+        assert_eq!(
+            SysEx::from_hex("F07E7"),
+            Err(SysExError::InvalidHex("F07E7".to_string()))
+        );
+    }
+
+    #[test]
+    fn validates_framing_and_data_bytes() {
+        assert_eq!(SysEx::from_hex(""), Err(SysExError::Empty));
+        assert_eq!(SysEx::from_hex("7E F7"), Err(SysExError::MissingStart));
+        assert_eq!(SysEx::from_hex("F0 7E"), Err(SysExError::MissingEnd));
+        assert_eq!(
+            SysEx::from_hex("F0 7E 80 F7"),
+            Err(SysExError::InvalidDataByte {
+                index: 2,
+                byte: 0x80
+            })
+        );
+    }
+
+    #[test]
+    fn to_hex_round_trips() {
+        let sysex = SysEx::from_hex("F0 00 01 74 10 F7").unwrap();
+        assert_eq!(sysex.to_hex(), "F0 00 01 74 10 F7");
+        assert_eq!(SysEx::from_hex(&sysex.to_hex()), Ok(sysex));
+    }
+}
@@ -0,0 +1,295 @@
+//! Incoming messages that trigger presets.
+//!
+//! A rule pairs a `MessagePattern` with a preset; whenever a message matching the pattern arrives
+//! on its input, the preset is played. Every rule that fires is written to a log so it is clear
+//! what the app reacted to. The patterns of the current rules are kept here, so messages no rule
+//! reacts to are dropped without reading storage.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+use crate::midi::channel::MidiChannel;
+use crate::midi::clock::now;
+use crate::midi::midi_message::MidiMessage;
+use crate::midi::value::U7;
+
+pub const MAX_LOG_ENTRIES: usize = 200;
+
+thread_local! {
+    static PATTERNS: RefCell<Vec<MessagePattern>> = const { RefCell::new(Vec::new()) };
+    static LOG: RefCell<VecDeque<FiredRule>> = const { RefCell::new(VecDeque::new()) };
+    /// Bumped whenever the log changes, so views can tell when to re-render.
+    static VERSION: Cell<u64> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Sequence, Serialize, Deserialize)]
+pub enum PatternKind {
+    #[default]
+    ProgramChange,
+    ControlChange,
+    NoteOn,
+}
+
+impl Display for PatternKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternKind::ProgramChange => write!(f, "Program Change"),
+            PatternKind::ControlChange => write!(f, "Control Change"),
+            PatternKind::NoteOn => write!(f, "Note On"),
+        }
+    }
+}
+
+/// Incoming messages a rule reacts to. `number` is the program, controller or note number and
+/// `value` the controller value or velocity; fields left at `None` match anything.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MessagePattern {
+    pub input: String,
+    pub kind: PatternKind,
+    pub channel: Option<MidiChannel>,
+    pub number: Option<U7>,
+    pub value: Option<U7>,
+}
+
+impl MessagePattern {
+    // `Option::is_none_or` would need a newer compiler than the crate asks for.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn matches(&self, port: &str, message: &MidiMessage) -> bool {
+        let (kind, channel, number, value) = match message {
+            MidiMessage::ProgramChange(pc) => {
+                (PatternKind::ProgramChange, pc.channel, pc.program, None)
+            }
+            MidiMessage::ControlChange(cc) => (
+                PatternKind::ControlChange,
+                cc.channel,
+                cc.control_number,
+                Some(cc.value),
+            ),
+            // A Note On with velocity 0 is a Note Off, sent when the key or switch is released.
+            MidiMessage::NoteOn(note) if note.velocity.get() == 0 => return false,
+            MidiMessage::NoteOn(note) => (
+                PatternKind::NoteOn,
+                note.channel,
+                note.note,
+                Some(note.velocity),
+            ),
+            _ => return false,
+        };
+        self.input == port
+            && self.kind == kind
+            && self.channel.map_or(true, |c| c == channel)
+            && self.number.map_or(true, |n| n == number)
+            && self.value.map_or(true, |v| Some(v) == value)
+    }
+}
+
+impl Display for MessagePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(number) = self.number {
+            write!(f, " {}", number)?;
+        }
+        if let Some(value) = self.value {
+            write!(f, " = {}", value)?;
+        }
+        match self.channel {
+            Some(channel) => write!(f, " on channel {}", channel)?,
+            None => write!(f, " on any channel")?,
+        }
+        write!(f, " from {}", self.input)
+    }
+}
+
+/// Replaces the patterns incoming messages are checked against.
+pub fn set_patterns(patterns: Vec<MessagePattern>) {
+    PATTERNS.with(|current| *current.borrow_mut() = patterns);
+}
+
+/// Whether `message`, received on `port`, matches any pattern passed to `set_patterns`.
+pub fn any_matches(port: &str, message: &MidiMessage) -> bool {
+    PATTERNS.with(|patterns| {
+        patterns
+            .borrow()
+            .iter()
+            .any(|pattern| pattern.matches(port, message))
+    })
+}
+
+/// A rule that fired, with the time it fired in milliseconds on the `now()` clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredRule {
+    pub timestamp: f64,
+    pub rule: usize,
+    pub preset: String,
+    pub message: MidiMessage,
+}
+
+/// Adds a fired rule to the log.
+pub fn log_fired(rule: usize, preset: &str, message: &MidiMessage) {
+    LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        log.push_back(FiredRule {
+            timestamp: now(),
+            rule,
+            preset: preset.to_string(),
+            message: message.clone(),
+        });
+        while log.len() > MAX_LOG_ENTRIES {
+            log.pop_front();
+        }
+    });
+    bump();
+}
+
+/// The rules that fired, oldest first.
+pub fn fired() -> Vec<FiredRule> {
+    LOG.with(|log| log.borrow().iter().cloned().collect())
+}
+
+pub fn clear_log() {
+    LOG.with(|log| log.borrow_mut().clear());
+    bump();
+}
+
+pub fn version() -> u64 {
+    VERSION.with(Cell::get)
+}
+
+fn bump() {
+    VERSION.with(|version| version.set(version.get() + 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::control_change::ControlChange;
+    use crate::midi::note::{NoteOff, NoteOn};
+    use crate::midi::program_change::ProgramChange;
+
+    fn channel(number: i32) -> MidiChannel {
+        MidiChannel::from_number(number).unwrap()
+    }
+
+    fn u7(value: u8) -> U7 {
+        U7::new(value).unwrap()
+    }
+
+    fn pattern(kind: PatternKind) -> MessagePattern {
+        MessagePattern {
+            input: "Pedal".to_string(),
+            kind,
+            ..MessagePattern::default()
+        }
+    }
+
+    fn program(channel_number: i32, program: u8) -> MidiMessage {
+        MidiMessage::ProgramChange(ProgramChange {
+            channel: channel(channel_number),
+            program: u7(program),
+        })
+    }
+
+    fn control(channel_number: i32, control_number: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange(ControlChange {
+            channel: channel(channel_number),
+            control_number: u7(control_number),
+            value: u7(value),
+        })
+    }
+
+    fn note_on(channel_number: i32, note: u8, velocity: u8) -> MidiMessage {
+        MidiMessage::NoteOn(NoteOn {
+            channel: channel(channel_number),
+            note: u7(note),
+            velocity: u7(velocity),
+        })
+    }
+
+    #[test]
+    fn matches_the_kind() {
+        let pattern = pattern(PatternKind::ControlChange);
+        assert!(pattern.matches("Pedal", &control(1, 64, 127)));
+        assert!(!pattern.matches("Pedal", &program(1, 64)));
+        assert!(!pattern.matches("Pedal", &note_on(1, 64, 127)));
+        assert!(!pattern.matches("Pedal", &MidiMessage::Start));
+    }
+
+    #[test]
+    fn matches_the_channel() {
+        let pattern = MessagePattern {
+            channel: Some(channel(2)),
+            ..pattern(PatternKind::ProgramChange)
+        };
+        assert!(pattern.matches("Pedal", &program(2, 5)));
+        assert!(!pattern.matches("Pedal", &program(1, 5)));
+    }
+
+    #[test]
+    fn matches_the_number_and_value() {
+        let pattern = MessagePattern {
+            number: Some(u7(64)),
+            value: Some(u7(127)),
+            ..pattern(PatternKind::ControlChange)
+        };
+        assert!(pattern.matches("Pedal", &control(1, 64, 127)));
+        assert!(!pattern.matches("Pedal", &control(1, 65, 127)));
+        assert!(!pattern.matches("Pedal", &control(1, 64, 0)));
+
+        // Program changes have no value, so a pattern asking for one never matches them.
+        let pattern = MessagePattern {
+            value: Some(u7(0)),
+            ..pattern.clone()
+        };
+        assert!(!MessagePattern {
+            kind: PatternKind::ProgramChange,
+            ..pattern
+        }
+        .matches("Pedal", &program(1, 64)));
+    }
+
+    #[test]
+    fn unset_fields_match_anything() {
+        let pattern = pattern(PatternKind::NoteOn);
+        for (channel_number, note, velocity) in [(1, 0, 1), (10, 60, 64), (16, 127, 127)] {
+            assert!(pattern.matches("Pedal", &note_on(channel_number, note, velocity)));
+        }
+    }
+
+    #[test]
+    fn matches_only_its_input() {
+        let pattern = pattern(PatternKind::ProgramChange);
+        assert!(!pattern.matches("Keyboard", &program(1, 5)));
+        assert!(!pattern.matches("pedal", &program(1, 5)));
+    }
+
+    #[test]
+    fn ignores_note_on_with_velocity_zero() {
+        let pattern = MessagePattern {
+            number: Some(u7(60)),
+            ..pattern(PatternKind::NoteOn)
+        };
+        assert!(pattern.matches("Pedal", &note_on(1, 60, 100)));
+        assert!(!pattern.matches("Pedal", &note_on(1, 60, 0)));
+        assert!(!pattern.matches(
+            "Pedal",
+            &MidiMessage::NoteOff(NoteOff {
+                channel: channel(1),
+                note: u7(60),
+                velocity: u7(0),
+            })
+        ));
+    }
+
+    #[test]
+    fn checks_messages_against_the_current_patterns() {
+        set_patterns(vec![pattern(PatternKind::ProgramChange)]);
+        assert!(any_matches("Pedal", &program(1, 5)));
+        assert!(!any_matches("Pedal", &control(1, 5, 5)));
+        set_patterns(vec![]);
+        assert!(!any_matches("Pedal", &program(1, 5)));
+    }
+}
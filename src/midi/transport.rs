@@ -0,0 +1,329 @@
+//! The backend that output ports are enumerated, opened and written through.
+//!
+//! Everything that sends MIDI goes through the current transport, which is `WebMidiTransport` in
+//! the browser and `MidirTransport` elsewhere. Tests replace it with a `LoopbackTransport`, which
+//! keeps everything in memory and records what was sent and when, so sequences can be checked
+//! without a device attached.
+
+use std::cell::RefCell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+#[cfg(any(test, target_arch = "wasm32"))]
+use std::collections::HashSet;
+#[cfg(test)]
+use std::rc::Rc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use midir::{MidiOutput, MidiOutputConnection};
+
+#[cfg(test)]
+use crate::midi::clock::now;
+use crate::midi::error::MidiError;
+#[cfg(target_arch = "wasm32")]
+use crate::midi::web_midi;
+
+#[cfg(not(target_arch = "wasm32"))]
+const CLIENT_NAME: &str = "My MIDI Output";
+#[cfg(not(target_arch = "wasm32"))]
+const CONNECTION_NAME: &str = "fractal-midi-rs";
+
+thread_local! {
+    static TRANSPORT: RefCell<Box<dyn MidiTransport>> = RefCell::new(default_transport());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_transport() -> Box<dyn MidiTransport> {
+    Box::new(WebMidiTransport::default())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_transport() -> Box<dyn MidiTransport> {
+    Box::new(MidirTransport::default())
+}
+
+pub trait MidiTransport {
+    /// Names of the output ports currently available, in enumeration order.
+    fn output_names(&self) -> Vec<String>;
+
+    /// Opens a connection to the output port called `name`.
+    fn connect(&mut self, name: &str) -> Result<(), MidiError>;
+
+    /// Closes the connection to `name`, returning whether one was open.
+    fn disconnect(&mut self, name: &str) -> bool;
+
+    /// Sends `message` to `name`, connecting first if needed.
+    fn send(&mut self, name: &str, message: &[u8]) -> Result<(), MidiError>;
+
+    /// Whether `send_at` hands messages to a scheduler that sends them on time, rather than
+    /// sending them straight away.
+    fn schedules_ahead(&self) -> bool {
+        false
+    }
+
+    /// Sends `message` to `name` at `timestamp`, in milliseconds on the `now()` clock. Transports
+    /// that do not schedule ahead send it right away.
+    fn send_at(&mut self, name: &str, message: &[u8], _timestamp: f64) -> Result<(), MidiError> {
+        self.send(name, message)
+    }
+
+    /// Drops messages passed to `send_at` that have not been sent yet.
+    fn clear_scheduled(&mut self) {}
+
+    /// Whether messages sent to `name` come straight back in on the input of the same name.
+    fn loops_back(&self, _name: &str) -> bool {
+        false
+    }
+}
+
+/// Replaces the transport all output goes through.
+#[cfg(test)]
+pub fn set_transport(transport: impl MidiTransport + 'static) {
+    TRANSPORT.with(|current| *current.borrow_mut() = Box::new(transport));
+}
+
+pub fn with_transport<R>(f: impl FnOnce(&mut dyn MidiTransport) -> R) -> R {
+    TRANSPORT.with(|transport| f(transport.borrow_mut().as_mut()))
+}
+
+/// Real devices in the browser, through Web MIDI. Nothing is listed until `permission::request`
+/// has been granted, and SysEx is refused unless it was granted too. Messages are handed to the
+/// browser with their timestamp, so they go out on time however busy the page is.
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct WebMidiTransport {
+    connected: HashSet<String>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl MidiTransport for WebMidiTransport {
+    fn output_names(&self) -> Vec<String> {
+        web_midi::output_names()
+    }
+
+    fn connect(&mut self, name: &str) -> Result<(), MidiError> {
+        web_midi::open_output(name)?;
+        self.connected.insert(name.to_string());
+        Ok(())
+    }
+
+    fn disconnect(&mut self, name: &str) -> bool {
+        let connected = self.connected.remove(name);
+        if connected {
+            web_midi::close_output(name);
+        }
+        connected
+    }
+
+    fn send(&mut self, name: &str, message: &[u8]) -> Result<(), MidiError> {
+        self.send_at(name, message, 0.0)
+    }
+
+    fn schedules_ahead(&self) -> bool {
+        web_midi::is_available()
+    }
+
+    fn send_at(&mut self, name: &str, message: &[u8], timestamp: f64) -> Result<(), MidiError> {
+        if !self.connected.contains(name) {
+            self.connect(name)?;
+        }
+        web_midi::send_at(name, message, timestamp)
+    }
+
+    fn clear_scheduled(&mut self) {
+        web_midi::clear()
+    }
+}
+
+/// Real devices, through midir. Connections stay open between messages, and a connection that
+/// fails to send is reopened once before giving up.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+pub struct MidirTransport {
+    connections: HashMap<String, MidiOutputConnection>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MidiTransport for MidirTransport {
+    fn output_names(&self) -> Vec<String> {
+        let Ok(midi_out) = MidiOutput::new(CLIENT_NAME) else {
+            return vec![];
+        };
+        midi_out
+            .ports()
+            .iter()
+            .filter_map(|port| midi_out.port_name(port).ok())
+            .collect()
+    }
+
+    fn connect(&mut self, name: &str) -> Result<(), MidiError> {
+        let midi_out = MidiOutput::new(CLIENT_NAME)?;
+        let port = midi_out
+            .ports()
+            .into_iter()
+            .find(|port| midi_out.port_name(port).ok().as_deref() == Some(name))
+            .ok_or_else(|| MidiError::PortNotFound(name.to_string()))?;
+        let connection =
+            midi_out
+                .connect(&port, CONNECTION_NAME)
+                .map_err(|err| MidiError::Connect {
+                    port: name.to_string(),
+                    reason: err.to_string(),
+                })?;
+        self.connections.insert(name.to_string(), connection);
+        Ok(())
+    }
+
+    fn disconnect(&mut self, name: &str) -> bool {
+        match self.connections.remove(name) {
+            Some(connection) => {
+                connection.close();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn send(&mut self, name: &str, message: &[u8]) -> Result<(), MidiError> {
+        if let Some(connection) = self.connections.get_mut(name) {
+            match connection.send(message) {
+                Ok(_) => return Ok(()),
+                Err(err) => log::warn!("Connection to {} lost ({}), reconnecting", name, err),
+            }
+            self.disconnect(name);
+        }
+        self.connect(name)?;
+        let connection = self
+            .connections
+            .get_mut(name)
+            .ok_or_else(|| MidiError::PortNotFound(name.to_string()))?;
+        connection.send(message).map_err(|err| MidiError::Send {
+            port: name.to_string(),
+            reason: err.to_string(),
+        })
+    }
+}
+
+/// A message sent through a `LoopbackTransport`, with the time it was sent in milliseconds on
+/// the `now()` clock.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentMessage {
+    pub at: f64,
+    pub port: String,
+    pub bytes: Vec<u8>,
+}
+
+/// In-memory ports that record everything sent to them. Clones share the record, so a copy kept
+/// before `set_transport` can be used to inspect what was sent.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct LoopbackTransport {
+    outputs: Vec<String>,
+    connected: HashSet<String>,
+    echo: bool,
+    ahead: bool,
+    sent: Rc<RefCell<Vec<SentMessage>>>,
+}
+
+#[cfg(test)]
+impl LoopbackTransport {
+    pub fn new(outputs: &[&str]) -> LoopbackTransport {
+        LoopbackTransport {
+            outputs: outputs.iter().map(|name| name.to_string()).collect(),
+            ..LoopbackTransport::default()
+        }
+    }
+
+    /// Also delivers everything sent to input listeners, as if each output were wired back to an
+    /// input of the same name.
+    pub fn with_echo(self) -> LoopbackTransport {
+        LoopbackTransport { echo: true, ..self }
+    }
+
+    /// Takes messages passed to `send_at` as scheduled for their timestamp, and records them
+    /// with it straight away. Like a browser without `MIDIOutput.clear()`, it cannot take them
+    /// back.
+    pub fn scheduling_ahead(self) -> LoopbackTransport {
+        LoopbackTransport {
+            ahead: true,
+            ..self
+        }
+    }
+
+    /// Everything sent so far, in order.
+    pub fn sent(&self) -> Vec<SentMessage> {
+        self.sent.borrow().clone()
+    }
+
+    /// The bytes sent to `port` so far, in order.
+    pub fn sent_to(&self, port: &str) -> Vec<Vec<u8>> {
+        self.sent
+            .borrow()
+            .iter()
+            .filter(|message| message.port == port)
+            .map(|message| message.bytes.clone())
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.sent.borrow_mut().clear();
+    }
+
+    pub fn is_connected(&self, name: &str) -> bool {
+        self.connected.contains(name)
+    }
+}
+
+#[cfg(test)]
+impl MidiTransport for LoopbackTransport {
+    fn output_names(&self) -> Vec<String> {
+        self.outputs.clone()
+    }
+
+    fn connect(&mut self, name: &str) -> Result<(), MidiError> {
+        if !self.outputs.iter().any(|output| output == name) {
+            return Err(MidiError::PortNotFound(name.to_string()));
+        }
+        self.connected.insert(name.to_string());
+        Ok(())
+    }
+
+    fn disconnect(&mut self, name: &str) -> bool {
+        self.connected.remove(name)
+    }
+
+    fn send(&mut self, name: &str, message: &[u8]) -> Result<(), MidiError> {
+        if !self.is_connected(name) {
+            self.connect(name)?;
+        }
+        self.sent.borrow_mut().push(SentMessage {
+            at: now(),
+            port: name.to_string(),
+            bytes: message.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn schedules_ahead(&self) -> bool {
+        self.ahead
+    }
+
+    fn send_at(&mut self, name: &str, message: &[u8], timestamp: f64) -> Result<(), MidiError> {
+        if !self.ahead {
+            return self.send(name, message);
+        }
+        if !self.is_connected(name) {
+            self.connect(name)?;
+        }
+        self.sent.borrow_mut().push(SentMessage {
+            at: timestamp,
+            port: name.to_string(),
+            bytes: message.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn loops_back(&self, name: &str) -> bool {
+        self.echo && self.outputs.iter().any(|output| output == name)
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MidiValueError {
+    ChannelOutOfRange(i32),
+    DataOutOfRange { value: i32, max: u16 },
+}
+
+impl Display for MidiValueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiValueError::ChannelOutOfRange(channel) => {
+                write!(f, "MIDI channel {} is not between 1 and 16", channel)
+            }
+            MidiValueError::DataOutOfRange { value, max } => {
+                write!(f, "Value {} is not between 0 and {}", value, max)
+            }
+        }
+    }
+}
+
+impl Error for MidiValueError {}
+
+/// A 7-bit MIDI data value (0–127).
+///
+/// Deserializing clamps out of range values instead of failing, so a single bad value in stored
+/// presets does not discard all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct U7(u8);
+
+impl U7 {
+    pub const MAX: u8 = 0x7F;
+
+    pub fn new(value: u8) -> Result<U7, MidiValueError> {
+        U7::try_from(value as i32)
+    }
+
+    /// Takes the low seven bits of a raw data byte.
+    pub fn from_masked(byte: u8) -> U7 {
+        U7(byte & U7::MAX)
+    }
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<i32> for U7 {
+    type Error = MidiValueError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match u8::try_from(value) {
+            Ok(v) if v <= U7::MAX => Ok(U7(v)),
+            _ => Err(MidiValueError::DataOutOfRange {
+                value,
+                max: U7::MAX as u16,
+            }),
+        }
+    }
+}
+
+impl From<U7> for u8 {
+    fn from(value: U7) -> u8 {
+        value.0
+    }
+}
+
+impl Display for U7 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for U7 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for U7 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u64::deserialize(deserializer)?;
+        Ok(U7(value.min(U7::MAX as u64) as u8))
+    }
+}
+
+/// A 14-bit MIDI data value (0–16383), sent as a pair of 7-bit bytes.
+///
+/// Deserializing clamps out of range values, like `U7`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct U14(u16);
+
+impl U14 {
+    pub const MAX: u16 = 0x3FFF;
+    pub const CENTER: U14 = U14(0x2000);
+
+    pub fn new(value: u16) -> Result<U14, MidiValueError> {
+        U14::try_from(value as i32)
+    }
+
+    pub fn from_parts(msb: u8, lsb: u8) -> U14 {
+        U14(((msb & U7::MAX) as u16) << 7 | (lsb & U7::MAX) as u16)
+    }
+
+    pub fn msb(self) -> u8 {
+        (self.0 >> 7) as u8
+    }
+
+    pub fn lsb(self) -> u8 {
+        (self.0 & U7::MAX as u16) as u8
+    }
+
+    pub fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<i32> for U14 {
+    type Error = MidiValueError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match u16::try_from(value) {
+            Ok(v) if v <= U14::MAX => Ok(U14(v)),
+            _ => Err(MidiValueError::DataOutOfRange {
+                value,
+                max: U14::MAX,
+            }),
+        }
+    }
+}
+
+impl From<U14> for u16 {
+    fn from(value: U14) -> u16 {
+        value.0
+    }
+}
+
+impl Display for U14 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for U14 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for U14 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u64::deserialize(deserializer)?;
+        Ok(U14(value.min(U14::MAX as u64) as u16))
+    }
+}
@@ -0,0 +1,72 @@
+//! Notices input and output ports appearing and disappearing.
+//!
+//! Neither midir nor the browser reliably report hot-plug events on every platform, so the
+//! watcher polls the port list and reports whenever it changes.
+
+use std::cell::RefCell;
+
+use gloo_timers::callback::Interval;
+
+use crate::midi::connection;
+use crate::midi::device::output_names;
+use crate::midi::input;
+use crate::midi::input::input_names;
+
+pub const POLL_INTERVAL_MS: u32 = 1000;
+
+/// Calls `on_change` with the names of all output ports whenever the list changes. Connections to
+/// ports that disappear are suspended and reopened when the port comes back. Watching stops when
+/// the returned interval is dropped.
+pub fn watch(on_change: impl Fn(Vec<String>) + 'static) -> Interval {
+    watch_ports(output_names, move |previous, outputs| {
+        for name in previous.iter().filter(|name| !outputs.contains(name)) {
+            log::warn!("MIDI output {} disconnected", name);
+            connection::suspend(name);
+        }
+        for name in outputs.iter().filter(|name| !previous.contains(name)) {
+            log::info!("MIDI output {} connected", name);
+            if let Err(err) = connection::restore(name) {
+                log::error!("Error reconnecting to {}: {}", name, err);
+            }
+        }
+        on_change(outputs);
+    })
+}
+
+/// Like `watch`, for input ports. Open inputs whose port disappears are reopened when it comes
+/// back.
+pub fn watch_inputs(on_change: impl Fn(Vec<String>) + 'static) -> Interval {
+    watch_ports(input_names, move |previous, inputs| {
+        for name in previous.iter().filter(|name| !inputs.contains(name)) {
+            log::warn!("MIDI input {} disconnected", name);
+            input::suspend(name);
+        }
+        for name in inputs.iter().filter(|name| !previous.contains(name)) {
+            log::info!("MIDI input {} connected", name);
+            if let Err(err) = input::restore(name) {
+                log::error!("Error reopening {}: {}", name, err);
+            }
+        }
+        on_change(inputs);
+    })
+}
+
+/// Polls `list` and calls `on_change` with the previous and the current names whenever they
+/// differ. The first poll always counts as a change, from no ports at all.
+fn watch_ports(
+    list: fn() -> Vec<String>,
+    on_change: impl Fn(&[String], Vec<String>) + 'static,
+) -> Interval {
+    let known: RefCell<Option<Vec<String>>> = RefCell::new(None);
+    Interval::new(POLL_INTERVAL_MS, move || {
+        let ports = list();
+        let previous = {
+            let mut known = known.borrow_mut();
+            if known.as_ref() == Some(&ports) {
+                return;
+            }
+            known.replace(ports.clone()).unwrap_or_default()
+        };
+        on_change(&previous, ports);
+    })
+}
@@ -0,0 +1,217 @@
+//! The browser's Web MIDI API, used directly rather than through midir.
+//!
+//! midir always asks for access including SysEx, so a user who refuses SysEx gets no MIDI at all.
+//! Here access is only requested when `request` is called, with SysEx only if it is needed, and
+//! access without SysEx is kept when SysEx alone is refused. Web MIDI also takes a timestamp with
+//! each message and leaves the timing to the browser, which keeps sending on schedule however
+//! busy the page is.
+
+use std::cell::RefCell;
+
+use js_sys::{Function, Reflect, Uint8Array};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{MidiAccess, MidiInput, MidiMessageEvent, MidiOptions, MidiOutput};
+
+use crate::midi::error::MidiError;
+use crate::midi::permission::Permission;
+use crate::midi::sysex::SYSEX_START;
+
+thread_local! {
+    static ACCESS: RefCell<Option<MidiAccess>> = const { RefCell::new(None) };
+    static PERMISSION: RefCell<Permission> = const { RefCell::new(Permission::NotRequested) };
+}
+
+pub fn permission() -> Permission {
+    PERMISSION.with(|permission| permission.borrow().clone())
+}
+
+/// Asks the browser for MIDI access, including SysEx if `sysex` is set. If SysEx is refused,
+/// access without it is requested instead and `MidiError::SysExDenied` returned, so everything
+/// else can still be sent.
+pub async fn request(sysex: bool) -> Result<(), MidiError> {
+    if !is_available() {
+        set_permission(Permission::Pending);
+    }
+    let (access, sysex_refused) = match request_access(sysex).await {
+        Ok(access) => (Ok(access), None),
+        Err(reason) if sysex => (request_access(false).await, Some(reason)),
+        Err(reason) => (Err(reason), None),
+    };
+    match access {
+        Ok(access) => {
+            set_permission(Permission::Granted {
+                sysex: access.sysex_enabled(),
+            });
+            ACCESS.with(|current| *current.borrow_mut() = Some(access));
+            match sysex_refused {
+                Some(reason) => Err(MidiError::SysExDenied(reason)),
+                None => Ok(()),
+            }
+        }
+        // Access granted earlier stays usable when asking for more fails.
+        Err(reason) if is_available() => Err(MidiError::SysExDenied(reason)),
+        Err(reason) => {
+            set_permission(Permission::Denied(reason.clone()));
+            Err(MidiError::Init(reason))
+        }
+    }
+}
+
+fn set_permission(permission: Permission) {
+    PERMISSION.with(|current| *current.borrow_mut() = permission);
+}
+
+async fn request_access(sysex: bool) -> Result<MidiAccess, String> {
+    let window = web_sys::window().ok_or_else(|| "no browser window".to_string())?;
+    let promise = window
+        .navigator()
+        .request_midi_access_with_options(MidiOptions::new().sysex(sysex))
+        .map_err(|err| reason(&err))?;
+    let access = JsFuture::from(promise).await.map_err(|err| reason(&err))?;
+    access.dyn_into().map_err(|err| reason(&err))
+}
+
+/// Whether MIDI access has been granted.
+pub fn is_available() -> bool {
+    ACCESS.with(|access| access.borrow().is_some())
+}
+
+fn sysex_enabled() -> bool {
+    ACCESS.with(|access| {
+        access
+            .borrow()
+            .as_ref()
+            .is_some_and(|access| access.sysex_enabled())
+    })
+}
+
+pub fn output_names() -> Vec<String> {
+    outputs().iter().map(|output| port_name(output)).collect()
+}
+
+pub fn input_names() -> Vec<String> {
+    inputs().iter().map(|input| port_name(input)).collect()
+}
+
+/// Starts opening the output port called `name`. The port also opens on the first send, so
+/// there is no need to wait for it.
+pub fn open_output(name: &str) -> Result<(), MidiError> {
+    let _ = output(name)?.open();
+    Ok(())
+}
+
+pub fn close_output(name: &str) {
+    if let Ok(output) = output(name) {
+        let _ = output.close();
+    }
+}
+
+/// Sends `message` to the output port called `name` at `timestamp`, in milliseconds on the
+/// `now()` clock. A timestamp of zero, or one in the past, sends right away.
+pub fn send_at(name: &str, message: &[u8], timestamp: f64) -> Result<(), MidiError> {
+    if message.first() == Some(&SYSEX_START) && !sysex_enabled() {
+        return Err(MidiError::SysExNotAllowed(name.to_string()));
+    }
+    output(name)?
+        .send_with_timestamp(&Uint8Array::from(message), timestamp)
+        .map_err(|err| MidiError::Send {
+            port: name.to_string(),
+            reason: reason(&err),
+        })
+}
+
+/// Drops the messages still waiting to be sent on every output. Not every browser implements
+/// `MIDIOutput.clear()`; where it is missing they go out regardless, which is why the scheduler
+/// only hands over what is due within its short look-ahead window.
+pub fn clear() {
+    for output in outputs() {
+        let clear = Reflect::get(&output, &JsValue::from_str("clear"))
+            .and_then(|clear| clear.dyn_into::<Function>());
+        if let Ok(clear) = clear {
+            let _ = clear.call0(&output);
+        }
+    }
+}
+
+/// An input port being listened on, until `close` is called.
+pub struct InputConnection {
+    input: MidiInput,
+    _on_message: Closure<dyn FnMut(MidiMessageEvent)>,
+}
+
+impl InputConnection {
+    pub fn close(self) {
+        self.input.set_onmidimessage(None);
+        let _ = self.input.close();
+    }
+}
+
+/// Calls `on_message` with everything received on the input port called `name`. Active sensing
+/// is dropped, as it only tells that the device is still there.
+pub fn connect_input(
+    name: &str,
+    on_message: impl Fn(&[u8]) + 'static,
+) -> Result<InputConnection, MidiError> {
+    let input = inputs()
+        .into_iter()
+        .find(|input| port_name(input) == name)
+        .ok_or_else(|| MidiError::PortNotFound(name.to_string()))?;
+    let on_message = Closure::<dyn FnMut(MidiMessageEvent)>::new(move |event: MidiMessageEvent| {
+        if let Ok(bytes) = event.data() {
+            if bytes != [ACTIVE_SENSING] {
+                on_message(&bytes);
+            }
+        }
+    });
+    input.set_onmidimessage(Some(on_message.as_ref().unchecked_ref()));
+    Ok(InputConnection {
+        input,
+        _on_message: on_message,
+    })
+}
+
+const ACTIVE_SENSING: u8 = 0xFE;
+
+fn output(name: &str) -> Result<MidiOutput, MidiError> {
+    outputs()
+        .into_iter()
+        .find(|output| port_name(output) == name)
+        .ok_or_else(|| MidiError::PortNotFound(name.to_string()))
+}
+
+fn outputs() -> Vec<MidiOutput> {
+    ACCESS.with(|access| match access.borrow().as_ref() {
+        Some(access) => ports(access.outputs().values()),
+        None => vec![],
+    })
+}
+
+fn inputs() -> Vec<MidiInput> {
+    ACCESS.with(|access| match access.borrow().as_ref() {
+        Some(access) => ports(access.inputs().values()),
+        None => vec![],
+    })
+}
+
+fn ports<T: JsCast>(values: js_sys::Iterator) -> Vec<T> {
+    values
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(JsCast::unchecked_into)
+        .collect()
+}
+
+/// The name a port is listed under, falling back to its id like midir does.
+fn port_name(port: &web_sys::MidiPort) -> String {
+    port.name().unwrap_or_else(|| port.id())
+}
+
+/// The message of a JavaScript exception, which is usually a `DOMException`.
+fn reason(err: &JsValue) -> String {
+    Reflect::get(err, &JsValue::from_str("message"))
+        .ok()
+        .and_then(|message| message.as_string())
+        .unwrap_or_else(|| format!("{:?}", err))
+}